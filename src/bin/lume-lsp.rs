@@ -1,6 +1,7 @@
 use std::error::Error;
+use std::path::PathBuf;
 
-use clap::{ArgAction, Parser, ValueHint};
+use clap::{ArgAction, Parser, Subcommand, ValueHint};
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -19,6 +20,42 @@ pub(crate) struct LumeLspCli {
 
     #[arg(long, short = 'v', help = "Enables verbose output", action = ArgAction::Count)]
     pub verbose: u8,
+
+    #[arg(
+        long,
+        group = "transport",
+        help = "Communicates with the client over standard I/O (default)"
+    )]
+    pub stdio: bool,
+
+    #[arg(
+        long,
+        value_name = "ADDR",
+        group = "transport",
+        help = "Listens for a client to connect on the given TCP address"
+    )]
+    pub socket: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ADDR",
+        group = "transport",
+        help = "Connects to a client listening on the given TCP address"
+    )]
+    pub connect: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<LumeLspCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum LumeLspCommand {
+    /// Checks the workspace once and prints all diagnostics, instead of
+    /// starting the language server.
+    Check {
+        #[arg(help = "Path to the root of the workspace", value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+    },
 }
 
 fn main() -> std::result::Result<(), Box<dyn Error + Sync + Send>> {
@@ -44,5 +81,65 @@ fn main() -> std::result::Result<(), Box<dyn Error + Sync + Send>> {
         simple_logging::log_to(std::io::stdout(), level_filter);
     }
 
-    lume_lsp::start_server()
+    if let Some(LumeLspCommand::Check { path }) = args.command {
+        if lume_lsp::check_workspace(&path) {
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    lume_lsp::start_server(args.transport())
+}
+
+impl LumeLspCli {
+    /// Gets the transport selected on the command line. Since the transport
+    /// flags are mutually exclusive, standard I/O is used if `--stdio` or no
+    /// flag at all is given.
+    fn transport(&self) -> lume_lsp::Transport {
+        if self.stdio {
+            return lume_lsp::Transport::Stdio;
+        }
+
+        match (&self.socket, &self.connect) {
+            (Some(addr), _) => lume_lsp::Transport::Socket(addr.clone()),
+            (None, Some(addr)) => lume_lsp::Transport::Connect(addr.clone()),
+            (None, None) => lume_lsp::Transport::Stdio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport_of(args: &[&str]) -> lume_lsp::Transport {
+        LumeLspCli::try_parse_from(std::iter::once("lume-lsp").chain(args.iter().copied()))
+            .unwrap()
+            .transport()
+    }
+
+    #[test]
+    fn transport_defaults_to_stdio() {
+        assert_eq!(transport_of(&[]), lume_lsp::Transport::Stdio);
+        assert_eq!(transport_of(&["--stdio"]), lume_lsp::Transport::Stdio);
+    }
+
+    #[test]
+    fn transport_over_tcp() {
+        assert_eq!(
+            transport_of(&["--socket", "127.0.0.1:9257"]),
+            lume_lsp::Transport::Socket(String::from("127.0.0.1:9257"))
+        );
+        assert_eq!(
+            transport_of(&["--connect", "127.0.0.1:9257"]),
+            lume_lsp::Transport::Connect(String::from("127.0.0.1:9257"))
+        );
+    }
+
+    #[test]
+    fn transports_are_exclusive() {
+        assert!(LumeLspCli::try_parse_from(["lume-lsp", "--stdio", "--socket", "127.0.0.1:9257"]).is_err());
+        assert!(LumeLspCli::try_parse_from(["lume-lsp", "--socket", "a:1", "--connect", "b:2"]).is_err());
+    }
 }
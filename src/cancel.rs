@@ -0,0 +1,43 @@
+use lsp_server::RequestId;
+use lsp_types::{CancelParams, NumberOrString};
+
+use crate::state::State;
+
+impl State {
+    /// Marks the given request as received, so it can be cancelled by the
+    /// client until it has been responded to.
+    pub(crate) fn register_request(&self, id: &RequestId) {
+        self.pending_requests.write().unwrap().insert(id.clone());
+    }
+
+    /// Marks the given request as handled, so it can no longer be cancelled.
+    pub(crate) fn finish_request(&self, id: &RequestId) {
+        self.pending_requests.write().unwrap().remove(id);
+        self.cancelled_requests.write().unwrap().remove(id);
+    }
+
+    /// Cancels the pending request from the given parameters. Requests which
+    /// have already been responded to are ignored.
+    pub(crate) fn cancel_request(&self, params: CancelParams) {
+        let id = match params.id {
+            NumberOrString::Number(id) => RequestId::from(id),
+            NumberOrString::String(id) => RequestId::from(id),
+        };
+
+        if !self.pending_requests.read().unwrap().contains(&id) {
+            log::debug!("ignoring cancellation of finished request {id}");
+            return;
+        }
+
+        log::info!("cancelled request {id}");
+
+        self.cancelled_requests.write().unwrap().insert(id);
+    }
+
+    /// Determines whether the given request was cancelled by the client, in
+    /// which case it should be responded to with
+    /// [`lsp_server::ErrorCode::RequestCanceled`].
+    pub(crate) fn is_cancelled(&self, id: &RequestId) -> bool {
+        self.cancelled_requests.read().unwrap().contains(id)
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use lsp_types::DiagnosticSeverity;
+use lume_errors::DiagCtx;
+
+use crate::config::Config;
+use crate::diagnostics::severity_of;
+use crate::exclude::ExcludeFilter;
+use crate::position::{PositionEncoding, position_from_range};
+
+/// Checks the workspace at the given root once, using the same pipeline as the
+/// language server, and prints all raised diagnostics to standard error.
+///
+/// Diagnostics within excluded paths are skipped, just like within the
+/// language server. Failing to load or check the workspace is reported as a
+/// diagnostic as well. Returns whether any errors were found.
+pub fn check_workspace(root: &Path) -> bool {
+    let config = Config::default();
+    let exclude = ExcludeFilter::new(root, &config.exclude, config.respect_gitignore);
+
+    let dcx = DiagCtx::new();
+
+    let result = lume_driver::Driver::from_root(root, dcx.handle())
+        .and_then(|driver| driver.check(lume_session::Options::default()));
+
+    if let Err(err) = result {
+        dcx.emit(err);
+    }
+
+    let mut has_errors = false;
+
+    dcx.with_iter(|diagnostics| {
+        for diagnostic in diagnostics {
+            let Some(rendered) = render_diagnostic(root, &exclude, diagnostic.as_ref()) else {
+                continue;
+            };
+
+            has_errors |= severity_of(diagnostic.severity()) == DiagnosticSeverity::ERROR;
+
+            eprintln!("{rendered}");
+        }
+    });
+
+    has_errors
+}
+
+/// Renders the given diagnostic into a human-readable form, pointing at the
+/// location of its primary label, if any.
+///
+/// If the diagnostic is located within an excluded path, [`None`] is returned.
+fn render_diagnostic(
+    root: &Path,
+    exclude: &ExcludeFilter,
+    diagnostic: &dyn error_snippet::Diagnostic,
+) -> Option<String> {
+    let severity = match severity_of(diagnostic.severity()) {
+        DiagnosticSeverity::ERROR => "error",
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::HINT => "help",
+        _ => "info",
+    };
+
+    let mut rendered = match diagnostic.code() {
+        Some(code) => format!("{severity}[{code}]: {}", diagnostic.message()),
+        None => format!("{severity}: {}", diagnostic.message()),
+    };
+
+    let primary_label = diagnostic
+        .labels()
+        .and_then(|labels| labels.into_iter().find(|label| label.source().is_some()));
+
+    if let Some(label) = primary_label
+        && let Some(source) = label.source()
+        && let Some(name) = source.name()
+    {
+        let path = Path::new(name);
+        let relative_path = path.strip_prefix(root).unwrap_or(path);
+
+        if exclude.is_excluded(relative_path) {
+            return None;
+        }
+
+        let range = position_from_range(source.content().as_ref(), &label.range().0, PositionEncoding::Utf8);
+
+        let _ = write!(
+            rendered,
+            "\n  --> {}:{}:{}: {}",
+            relative_path.display(),
+            range.start.line + 1,
+            range.start.character + 1,
+            label.message()
+        );
+    }
+
+    if let Some(help_notes) = diagnostic.help() {
+        for help_note in help_notes {
+            let _ = write!(rendered, "\n  = help: {}", help_note.message);
+        }
+    }
+
+    Some(rendered)
+}
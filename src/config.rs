@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use lsp_server::Message;
+use lsp_types::request::{InlayHintRefreshRequest, Request as _, WorkspaceConfiguration};
+use lsp_types::{ConfigurationItem, ConfigurationParams, DiagnosticSeverity, ServerCapabilities, Uri};
+use serde::Deserialize;
+
+use crate::state::State;
+
+/// Prefix of the IDs of requests which fetch the configuration from the
+/// client.
+const CONFIGURATION_REQUEST_PREFIX: &str = "lume/configuration";
+
+/// Section of the client settings which contains the configuration.
+const CONFIGURATION_SECTION: &str = "lume";
+
+/// Default URL of the documentation of diagnostic codes, where `{code}` is
+/// replaced with the code of the diagnostic.
+const DEFAULT_DIAGNOSTIC_DOCS_URL: &str = "https://lume-lang.org/errors/{code}";
+
+/// Default extensions of the files which are managed by the server.
+const DEFAULT_FILE_EXTENSIONS: &[&str] = &["lm", "arc"];
+
+/// User-defined configuration of the language server, which is passed by the
+/// client within the initialization options.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Config {
+    /// URL of the documentation of diagnostic codes, where `{code}` is
+    /// replaced with the code of the diagnostic.
+    pub diagnostic_docs_url: String,
+
+    /// Extensions of the files which are managed by the server. Documents with
+    /// any other extension are ignored.
+    pub file_extensions: Vec<String>,
+
+    /// Glob patterns of paths, relative to the workspace root, whose open
+    /// documents are never passed to the compiler and whose diagnostics are
+    /// never reported. Files which the compiler discovers on disk as part of a
+    /// package are still compiled.
+    pub exclude: Vec<String>,
+
+    /// Whether the paths within the `.gitignore` file at the workspace root
+    /// should be excluded as well.
+    pub respect_gitignore: bool,
+
+    /// Whether inlay hints should be shown for inferred types, under the
+    /// `enableInlayHints` key.
+    #[serde(alias = "inlayHints")]
+    pub enable_inlay_hints: bool,
+
+    /// Whether semantic tokens should be provided, under the
+    /// `enableSemanticTokens` key.
+    pub enable_semantic_tokens: bool,
+
+    /// Whether completion items should be provided, under the
+    /// `enableCompletion` key.
+    pub enable_completion: bool,
+
+    /// Whether commands for debugging the server itself should be available,
+    /// under the `debugFeatures` key.
+    pub debug_features: bool,
+
+    /// Overrides of the severity of diagnostics, keyed by either their
+    /// diagnostic code or the name of the severity raised by the compiler,
+    /// such as `warning`. Overrides of codes take precedence.
+    pub diagnostic_severity: HashMap<String, SeverityOverride>,
+
+    /// How diagnostics within files outside of the workspace roots, such as
+    /// dependency packages, should be reported.
+    pub dependency_diagnostics: DependencyDiagnostics,
+
+    /// Whether edits to a document should only recheck the workspace roots
+    /// whose package graph contains the package of the document, under the
+    /// `checkAffectedRootsOnly` key.
+    pub check_affected_roots_only: bool,
+}
+
+/// How diagnostics within files outside of the workspace roots are reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DependencyDiagnostics {
+    /// Diagnostics aren't reported at all.
+    #[default]
+    Off,
+
+    /// Diagnostics are reported as information, prefixed to mark them as
+    /// originating from a dependency.
+    Information,
+}
+
+/// Severity which is reported for all diagnostics with some code or severity,
+/// instead of the severity raised by the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum SeverityOverride {
+    Error,
+    Warning,
+    Information,
+    Hint,
+
+    /// Diagnostics aren't reported at all.
+    Off,
+}
+
+impl SeverityOverride {
+    /// Gets the severity to report, or [`None`] if the diagnostic should not be
+    /// reported.
+    pub fn severity(self) -> Option<DiagnosticSeverity> {
+        match self {
+            SeverityOverride::Error => Some(DiagnosticSeverity::ERROR),
+            SeverityOverride::Warning => Some(DiagnosticSeverity::WARNING),
+            SeverityOverride::Information => Some(DiagnosticSeverity::INFORMATION),
+            SeverityOverride::Hint => Some(DiagnosticSeverity::HINT),
+            SeverityOverride::Off => None,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            diagnostic_docs_url: String::from(DEFAULT_DIAGNOSTIC_DOCS_URL),
+            file_extensions: DEFAULT_FILE_EXTENSIONS.iter().map(|ext| (*ext).to_owned()).collect(),
+            exclude: Vec::new(),
+            respect_gitignore: true,
+            enable_inlay_hints: true,
+            enable_semantic_tokens: true,
+            enable_completion: true,
+            debug_features: false,
+            diagnostic_severity: HashMap::new(),
+            dependency_diagnostics: DependencyDiagnostics::default(),
+            check_affected_roots_only: false,
+        }
+    }
+}
+
+impl Config {
+    /// Parses the configuration from the given initialization options.
+    ///
+    /// If no options are given or if they are malformed, the default
+    /// configuration is used instead.
+    pub fn from_options(options: Option<&serde_json::Value>) -> Self {
+        let Some(options) = options.filter(|options| !options.is_null()) else {
+            return Self::default();
+        };
+
+        match serde_json::from_value(options.clone()) {
+            Ok(config) => config,
+            Err(err) => {
+                log::warn!("invalid initialization options, using defaults: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Parses the configuration from the settings which are sent by the client,
+    /// either within the `lume` section or at the top-level.
+    pub fn from_settings(settings: &serde_json::Value) -> Self {
+        Self::from_options(Some(settings.get(CONFIGURATION_SECTION).unwrap_or(settings)))
+    }
+
+    /// Gets the severity to report for a diagnostic with the given code and
+    /// severity, as raised by the compiler.
+    ///
+    /// If the diagnostic is disabled, [`None`] is returned.
+    pub fn severity_of(&self, code: Option<&str>, severity: DiagnosticSeverity) -> Option<DiagnosticSeverity> {
+        let severity_name = match severity {
+            DiagnosticSeverity::ERROR => "error",
+            DiagnosticSeverity::WARNING => "warning",
+            DiagnosticSeverity::HINT => "hint",
+            _ => "information",
+        };
+
+        let severity_override = code
+            .and_then(|code| self.diagnostic_severity.get(code))
+            .or_else(|| self.diagnostic_severity.get(severity_name));
+
+        match severity_override {
+            Some(severity_override) => severity_override.severity(),
+            None => Some(severity),
+        }
+    }
+
+    /// Removes the capabilities of all features which are disabled within the
+    /// configuration, so the client never requests them, and adds the commands
+    /// of debug features, if enabled.
+    ///
+    /// Since capabilities are only advertised once, features which are disabled
+    /// after initialization are still advertised, so handlers must check the
+    /// configuration as well.
+    pub fn configure_capabilities(&self, capabilities: &mut ServerCapabilities) {
+        if !self.enable_inlay_hints {
+            capabilities.inlay_hint_provider = None;
+        }
+
+        if !self.enable_semantic_tokens {
+            capabilities.semantic_tokens_provider = None;
+        }
+
+        if !self.enable_completion {
+            capabilities.completion_provider = None;
+        }
+
+        if self.debug_features
+            && let Some(execute_command) = &mut capabilities.execute_command_provider
+        {
+            execute_command.commands.push(String::from(crate::COMMAND_DUMP_HIR));
+        }
+    }
+
+    /// Determines whether switching from this configuration to the given one
+    /// changes which files are checked or how their diagnostics are reported,
+    /// meaning the workspace must be checked again.
+    pub fn affects_checking(&self, other: &Config) -> bool {
+        self.file_extensions != other.file_extensions
+            || self.exclude != other.exclude
+            || self.respect_gitignore != other.respect_gitignore
+            || self.diagnostic_docs_url != other.diagnostic_docs_url
+            || self.diagnostic_severity != other.diagnostic_severity
+            || self.dependency_diagnostics != other.dependency_diagnostics
+    }
+
+    /// Gets the URL of the documentation of the given diagnostic code.
+    pub fn diagnostic_docs_url_of(&self, code: &str) -> String {
+        self.diagnostic_docs_url.replace("{code}", code)
+    }
+
+    /// Determines whether the document with the given URI should be managed by
+    /// the server, based on its file extension. `Arcfile`s have no extension,
+    /// so they are always tracked by their name instead.
+    pub fn is_tracked(&self, uri: &Uri) -> bool {
+        let path = Path::new(uri.path().as_str());
+
+        if path.file_name().is_some_and(|name| name == "Arcfile") {
+            return true;
+        }
+
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.file_extensions.iter().any(|tracked| tracked == ext))
+    }
+}
+
+impl State {
+    /// Requests the configuration from the client, if supported. The response
+    /// is handled in the listening loop.
+    pub(crate) fn request_configuration(&self) {
+        if !self.configuration_support {
+            return;
+        }
+
+        let id = self.next_request_id(CONFIGURATION_REQUEST_PREFIX);
+
+        // Only the response to the latest request is applied, so responses to earlier
+        // requests don't replace a newer configuration.
+        *self.pending_configuration.write().unwrap() = Some(id.clone());
+
+        let request = lsp_server::Request::new(id, WorkspaceConfiguration::METHOD.to_owned(), ConfigurationParams {
+            items: vec![ConfigurationItem {
+                scope_uri: None,
+                section: Some(CONFIGURATION_SECTION.to_owned()),
+            }],
+        });
+
+        if let Err(err) = self.dispatcher.send(Message::Request(request)) {
+            log::error!("could not request configuration: {err}");
+        }
+    }
+
+    /// Replaces the configuration of the server and applies it to the entire
+    /// workspace, by refreshing all inlay hints.
+    ///
+    /// The workspace is only checked again if the new configuration changes
+    /// which files are checked or how their diagnostics are reported.
+    pub(crate) fn update_config(&mut self, config: Config) {
+        if config == self.config {
+            log::debug!("configuration is unchanged");
+            return;
+        }
+
+        log::info!("updated configuration: {config:?}");
+
+        let affects_checking = self.config.affects_checking(&config);
+        self.config = config;
+
+        if affects_checking {
+            // Reload the exclusion filters, since the patterns might have changed.
+            self.set_workspace_roots(self.workspace_root_uris());
+            self.compile_workspace();
+        }
+
+        if self.inlay_hint_refresh_support {
+            let request = lsp_server::Request::new(
+                self.next_request_id("lume/refresh-inlay-hints"),
+                InlayHintRefreshRequest::METHOD.to_owned(),
+                (),
+            );
+
+            if let Err(err) = self.dispatcher.send(Message::Request(request)) {
+                log::error!("could not refresh inlay hints: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_overrides(overrides: &[(&str, SeverityOverride)]) -> Config {
+        Config {
+            diagnostic_severity: overrides
+                .iter()
+                .map(|(key, severity)| ((*key).to_owned(), *severity))
+                .collect(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn severity_is_kept_without_overrides() {
+        let config = Config::default();
+
+        assert_eq!(
+            config.severity_of(Some("unused-variable"), DiagnosticSeverity::WARNING),
+            Some(DiagnosticSeverity::WARNING)
+        );
+    }
+
+    #[test]
+    fn severity_is_downgraded() {
+        let config = config_with_overrides(&[("warning", SeverityOverride::Hint)]);
+
+        assert_eq!(
+            config.severity_of(None, DiagnosticSeverity::WARNING),
+            Some(DiagnosticSeverity::HINT)
+        );
+        assert_eq!(
+            config.severity_of(None, DiagnosticSeverity::ERROR),
+            Some(DiagnosticSeverity::ERROR)
+        );
+    }
+
+    #[test]
+    fn severity_is_suppressed() {
+        let config = config_with_overrides(&[("information", SeverityOverride::Off)]);
+
+        assert_eq!(config.severity_of(None, DiagnosticSeverity::INFORMATION), None);
+    }
+
+    #[test]
+    fn code_overrides_take_precedence() {
+        let config = config_with_overrides(&[
+            ("warning", SeverityOverride::Off),
+            ("unused-variable", SeverityOverride::Error),
+        ]);
+
+        assert_eq!(
+            config.severity_of(Some("unused-variable"), DiagnosticSeverity::WARNING),
+            Some(DiagnosticSeverity::ERROR)
+        );
+        assert_eq!(config.severity_of(Some("other"), DiagnosticSeverity::WARNING), None);
+    }
+
+    #[test]
+    fn settings_are_read_from_section() {
+        let settings = serde_json::json!({
+            "lume": {
+                "diagnosticSeverity": { "warning": "off" },
+                "enableInlayHints": false,
+            }
+        });
+
+        let config = Config::from_settings(&settings);
+
+        assert!(!config.enable_inlay_hints);
+        assert_eq!(config.diagnostic_severity.get("warning"), Some(&SeverityOverride::Off));
+    }
+
+    #[test]
+    fn settings_are_read_from_top_level() {
+        let settings = serde_json::json!({ "enableCompletion": false });
+
+        assert!(!Config::from_settings(&settings).enable_completion);
+    }
+
+    #[test]
+    fn malformed_settings_use_defaults() {
+        let settings = serde_json::json!({ "lume": { "fileExtensions": 42 } });
+
+        assert_eq!(Config::from_settings(&settings), Config::default());
+        assert_eq!(Config::from_options(None), Config::default());
+        assert_eq!(Config::from_options(Some(&serde_json::Value::Null)), Config::default());
+    }
+
+    fn uri(text: &str) -> Uri {
+        <Uri as std::str::FromStr>::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn documents_are_tracked_by_extension() {
+        let config = Config::default();
+
+        assert!(config.is_tracked(&uri("file:///project/src/main.lm")));
+        assert!(config.is_tracked(&uri("file:///project/build.arc")));
+        assert!(!config.is_tracked(&uri("file:///project/README.md")));
+        assert!(!config.is_tracked(&uri("file:///project/lm")));
+    }
+
+    #[test]
+    fn arcfiles_are_tracked_by_name() {
+        let config = Config {
+            file_extensions: Vec::new(),
+            ..Config::default()
+        };
+
+        assert!(config.is_tracked(&uri("file:///project/Arcfile")));
+        assert!(!config.is_tracked(&uri("file:///project/Arcfile.bak")));
+        assert!(!config.is_tracked(&uri("file:///project/main.lm")));
+    }
+}
@@ -0,0 +1,25 @@
+use lsp_types::Uri;
+use lume_hir::WithLocation as _;
+
+use crate::state::State;
+
+/// Renders all HIR nodes which are declared within the document with the
+/// given URI, as they were built by the last successful check.
+///
+/// Returns [`None`] if the document isn't part of any package.
+pub(crate) fn dump_hir_of(state: &State, uri: &Uri) -> Option<String> {
+    let source_file = state.source_of_uri(uri)?;
+    let package = state.checked.graph.packages.get(&source_file.package)?;
+
+    let dump = package
+        .tcx
+        .hir()
+        .nodes()
+        .values()
+        .filter(|node| node.location().file.id == source_file.id)
+        .map(|node| format!("{node:#?}"))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    Some(dump)
+}
@@ -1,11 +1,16 @@
 use std::fmt::Write as _;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use indexmap::IndexMap;
 use lsp_server::Message;
 use lsp_types::notification::*;
+use lsp_types::request::Request as _;
 use lsp_types::*;
 
+use crate::config::DependencyDiagnostics;
+use crate::position::position_from_range;
 use crate::state::State;
 
 pub const LSP_SOURCE_LUME: &str = "lume";
@@ -14,9 +19,90 @@ impl State {
     /// Drain all diagnostics from the inner diagnostics context to
     /// the language client.
     pub(crate) fn drain_dcx_diagnostics(&self) {
+        self.flush_dcx_diagnostics();
+
+        // Take all the files which had one-or-more diagnostics, but no longer do and
+        // push an empty list of diagnostics to the client. Since the previous set is
+        // replaced on each compilation, each file is only cleared once.
+        let curr = self.error_files_curr.read().unwrap();
+        let prev = self.error_files_prev.read().unwrap();
+
+        for file_url in prev.iter().filter(|uri| !curr.contains_key(*uri)) {
+            self.publish_diagnostics_to_file(&[], file_url.clone());
+        }
+
+        if self.pull_diagnostics {
+            self.refresh_diagnostics();
+        }
+    }
+
+    /// Gets the diagnostics of the file with the given URI from the latest
+    /// check, for clients which pull diagnostics.
+    pub(crate) fn diagnostics_of_file(&self, uri: &Uri) -> Vec<Diagnostic> {
+        self.error_files_curr
+            .read()
+            .unwrap()
+            .get(uri)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Gets the ID of the given diagnostics report, which only changes when the
+    /// diagnostics themselves change, so clients can be told that a report is
+    /// unchanged since their last pull.
+    pub(crate) fn result_id_of(diagnostics: &[Diagnostic]) -> String {
+        let mut hasher = DefaultHasher::new();
+
+        // Diagnostics don't implement `Hash`, but their serialized form uniquely
+        // identifies them.
+        serde_json::to_string(diagnostics).unwrap_or_default().hash(&mut hasher);
+
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Requests the client to pull the diagnostics of all documents again.
+    fn refresh_diagnostics(&self) {
+        let request = lsp_server::Request::new(
+            self.next_request_id("lume/refresh-diagnostics"),
+            lsp_types::request::WorkspaceDiagnosticRefresh::METHOD.to_owned(),
+            (),
+        );
+
+        if let Err(err) = self.dispatcher.send(Message::Request(request)) {
+            log::error!("could not refresh diagnostics: {err}");
+        }
+    }
+
+    /// Publishes all diagnostics from the inner diagnostics context to the
+    /// language client, while the workspace is still being checked.
+    ///
+    /// Unlike [`State::drain_dcx_diagnostics`], files which no longer have any
+    /// diagnostics are not cleared, since their diagnostics may not have been
+    /// raised yet.
+    pub(crate) fn flush_dcx_diagnostics(&self) {
+        let mut lowered = Vec::new();
+
         self.dcx.with_iter(|diagnostics| {
             for diagnostic in diagnostics {
-                self.publish_diagnostic(diagnostic.as_ref());
+                let Some((uri, mut diagnostic)) = self.lower_diagnostic(diagnostic.as_ref()) else {
+                    continue;
+                };
+
+                if self.is_excluded(&uri) {
+                    continue;
+                }
+
+                if !self.is_workspace_file(&uri) {
+                    match self.config.dependency_diagnostics {
+                        DependencyDiagnostics::Off => continue,
+                        DependencyDiagnostics::Information => {
+                            diagnostic.severity = Some(DiagnosticSeverity::INFORMATION);
+                            diagnostic.message = format!("from dependency: {}", diagnostic.message);
+                        }
+                    }
+                }
+
+                lowered.push((uri, diagnostic));
             }
         });
 
@@ -24,38 +110,75 @@ impl State {
         // be reported on the next drain either.
         self.dcx.clear();
 
-        // Take all the files which had one-or-more diagnostics, but no longer do and
-        // push an empty list of diagnostics to the client.
-        let prev = self.error_files_prev.read().unwrap();
-        let curr = self.error_files_curr.read().unwrap();
+        self.publish_per_file(lowered);
+    }
 
-        for file_url in prev.difference(&curr) {
-            self.publish_diagnostics_to_file(&[], file_url.clone());
+    /// Publishes the given diagnostics, along with all diagnostics which were
+    /// already published during the current check, with a single publish per
+    /// file.
+    fn publish_per_file(&self, lowered: Vec<(Uri, Diagnostic)>) {
+        // Each publish replaces all existing diagnostics of the file, so all
+        // diagnostics must be grouped by their file before being published.
+        let mut diagnostics_per_file: IndexMap<Uri, Vec<Diagnostic>> = IndexMap::new();
+
+        for (uri, diagnostic) in lowered {
+            diagnostics_per_file.entry(uri).or_default().push(diagnostic);
+        }
+
+        let mut curr = self.error_files_curr.write().unwrap();
+
+        // Diagnostics which were already published to a file during the current
+        // check must be published again, so they aren't replaced by the new ones.
+        for (uri, diagnostics) in diagnostics_per_file {
+            let published = curr.entry(uri.clone()).or_default();
+            published.extend(diagnostics);
+
+            self.publish_diagnostics_to_file(published, uri);
         }
     }
 
-    /// Publishes the given [`error_snippet::Diagnostic`] to the language
-    /// client.
-    pub(crate) fn publish_diagnostic(&self, diagnostic: &dyn error_snippet::Diagnostic) {
-        let Some(labels) = diagnostic.labels() else {
-            return;
-        };
+    /// Gets all diagnostics which are currently published to the client, as a
+    /// flat JSON array of objects with the URI, range, severity, code and
+    /// message of each diagnostic.
+    ///
+    /// Since published diagnostics are replaced on each check, the array always
+    /// reflects the latest check of the workspace.
+    pub(crate) fn export_diagnostics(&self) -> serde_json::Value {
+        let curr = self.error_files_curr.read().unwrap();
+
+        let diagnostics = curr
+            .iter()
+            .flat_map(|(uri, diagnostics)| {
+                diagnostics.iter().map(move |diagnostic| {
+                    serde_json::json!({
+                        "uri": uri,
+                        "range": diagnostic.range,
+                        "severity": diagnostic.severity,
+                        "code": diagnostic.code,
+                        "message": diagnostic.message,
+                    })
+                })
+            })
+            .collect();
+
+        serde_json::Value::Array(diagnostics)
+    }
+
+    /// Lowers the given [`error_snippet::Diagnostic`] into a diagnostic for the
+    /// language client, along with the URI of the file it should be published
+    /// to.
+    ///
+    /// If the diagnostic has no labels with source content attached or if it
+    /// is disabled within the configuration, [`None`] is returned.
+    fn lower_diagnostic(&self, diagnostic: &dyn error_snippet::Diagnostic) -> Option<(Uri, Diagnostic)> {
+        let labels = diagnostic.labels()?;
 
         let labels = labels
             .into_iter()
             .filter_map(|label| self.lower_diagnostic_label(&label))
             .collect::<Vec<_>>();
 
-        for label in &labels {
-            self.error_files_curr
-                .write()
-                .unwrap()
-                .insert(label.location.uri.clone());
-        }
-
-        let Some((primary_label, related)) = labels.split_first() else {
-            return;
-        };
+        let (primary_label, related) = labels.split_first()?;
 
         let related_info = related
             .iter()
@@ -65,39 +188,62 @@ impl State {
             })
             .collect();
 
-        let severity = match diagnostic.severity() {
-            error_snippet::Severity::Note | error_snippet::Severity::Info => DiagnosticSeverity::INFORMATION,
-            error_snippet::Severity::Help => DiagnosticSeverity::HINT,
-            error_snippet::Severity::Warning => DiagnosticSeverity::WARNING,
-            error_snippet::Severity::Error => DiagnosticSeverity::ERROR,
-        };
+        let code = diagnostic.code().map(|code| code.to_string());
+
+        // Overrides from the configuration take precedence over the severity raised by
+        // the compiler, including disabling the diagnostic entirely.
+        let severity = self
+            .config
+            .severity_of(code.as_deref(), severity_of(diagnostic.severity()))?;
 
-        let code = diagnostic.code().map(|code| NumberOrString::String(code.to_string()));
+        let code_description = code.as_ref().and_then(|code| {
+            let href = self.config.diagnostic_docs_url_of(code);
 
+            match Uri::from_str(&href) {
+                Ok(href) => Some(CodeDescription { href }),
+                Err(err) => {
+                    log::warn!("invalid diagnostic documentation URL `{href}`: {err}");
+                    None
+                }
+            }
+        });
+
+        // Help notes have no location of their own, so they can't be reported as
+        // related information. Instead, they're separated from the message, so
+        // they stand out from the message itself.
         let mut message = primary_label.message.clone();
         if let Some(help_notes) = diagnostic.help() {
-            for help_note in help_notes {
-                let _ = write!(message, "\n{}", help_note.message);
+            for (idx, help_note) in help_notes.into_iter().enumerate() {
+                let separator = if idx == 0 { "\n\n" } else { "\n" };
+
+                let _ = write!(message, "{separator}help: {}", help_note.message);
             }
         }
 
         let diag = Diagnostic {
             range: primary_label.location.range,
             severity: Some(severity),
-            code,
-            code_description: None,
+            code: code.map(NumberOrString::String),
+            code_description,
             source: Some(String::from(LSP_SOURCE_LUME)),
             message,
             related_information: Some(related_info),
-            tags: None,
+            tags: tags_for(diagnostic),
             data: None,
         };
 
-        self.publish_diagnostics_to_file(&[diag], primary_label.location.uri.clone());
+        Some((primary_label.location.uri.clone(), diag))
     }
 
     /// Publishes the given [`DiagnosticDiagnostic`] to the given file.
+    ///
+    /// If the client pulls diagnostics, nothing is published, since the client
+    /// is refreshed once the check has finished.
     pub(crate) fn publish_diagnostics_to_file(&self, diag: &[Diagnostic], file: Uri) {
+        if self.pull_diagnostics {
+            return;
+        }
+
         let params = PublishDiagnosticsParams {
             uri: file,
             diagnostics: diag.to_vec(),
@@ -118,22 +264,12 @@ impl State {
     /// returned.
     fn lower_diagnostic_label(&self, label: &error_snippet::Label) -> Option<DiagnosticLabel> {
         let source = label.source()?;
-        let position = position_from_range(source.content().as_ref(), &label.range().0);
+        let position = position_from_range(source.content().as_ref(), &label.range().0, self.position_encoding);
 
         let file_path = PathBuf::from(source.name()?);
 
         // Canonicalize the path to an absolute path, if not already.
-        let uri = if file_path.has_root() {
-            let file_path = format!("file://{}", file_path.display());
-
-            Uri::from_str(file_path.as_str()).unwrap()
-        } else {
-            let root = PathBuf::from(self.vfs.workspace_root.as_str());
-            let absolute = root.join(file_path.as_os_str().to_str().unwrap());
-            let file_path = format!("file://{}", absolute.display());
-
-            Uri::from_str(file_path.as_str()).unwrap()
-        };
+        let uri = self.uri_of_path(&file_path)?;
 
         Some(DiagnosticLabel {
             location: Location { uri, range: position },
@@ -142,34 +278,249 @@ impl State {
     }
 }
 
+/// Maps the severity of a compiler diagnostic onto the closest severity of a
+/// language client diagnostic.
+pub(crate) fn severity_of(severity: error_snippet::Severity) -> DiagnosticSeverity {
+    match severity {
+        error_snippet::Severity::Note | error_snippet::Severity::Info => DiagnosticSeverity::INFORMATION,
+        error_snippet::Severity::Help => DiagnosticSeverity::HINT,
+        error_snippet::Severity::Warning => DiagnosticSeverity::WARNING,
+        error_snippet::Severity::Error => DiagnosticSeverity::ERROR,
+    }
+}
+
+/// Code of diagnostics which are raised for variables which are declared, but
+/// never used.
+pub(crate) const UNUSED_VARIABLE_CODE: &str = "unused-variable";
+
+/// Codes of diagnostics which are raised for references to types which don't
+/// exist within the current scope.
+pub(crate) const MISSING_TYPE_CODES: &[&str] = &["missing-type", "unknown-type"];
+
+/// Codes of diagnostics which are raised for unused or unreachable code, which
+/// clients render as faded. Each code is checked against the diagnostics the
+/// compiler raises on a fixture workspace in the tests below.
+const UNNECESSARY_CODES: &[&str] = &[UNUSED_VARIABLE_CODE, "unused-import", "unreachable-code"];
+
+/// Codes of diagnostics which are raised for uses of deprecated symbols, which
+/// clients render as struck through.
+const DEPRECATED_CODES: &[&str] = &["deprecated"];
+
+/// Gets the tags which apply to the given diagnostic, so clients can render
+/// unused code as faded and deprecated symbols as struck through.
+///
+/// Since the compiler doesn't attach any tags to its diagnostics, they are
+/// derived from the code of the diagnostic. Diagnostics without a code never
+/// have any tags.
+fn tags_for(diagnostic: &dyn error_snippet::Diagnostic) -> Option<Vec<DiagnosticTag>> {
+    tags_of_code(&diagnostic.code()?.to_string())
+}
+
+/// Gets the tags which apply to diagnostics with the given code.
+fn tags_of_code(code: &str) -> Option<Vec<DiagnosticTag>> {
+    if DEPRECATED_CODES.contains(&code) {
+        Some(vec![DiagnosticTag::DEPRECATED])
+    } else if UNNECESSARY_CODES.contains(&code) {
+        Some(vec![DiagnosticTag::UNNECESSARY])
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 struct DiagnosticLabel {
     pub location: Location,
     pub message: String,
 }
 
-fn position_from_range(text: &str, range: &std::ops::Range<usize>) -> Range {
-    let start = position_from_index(text, range.start);
-    let end = position_from_index(text, range.end);
+#[cfg(test)]
+mod tests {
+    use crossbeam::channel::Receiver;
+
+    use super::*;
+    use crate::state::tests::{checked_test_state, test_arcfile, test_state};
+
+    /// Gets all diagnostics which were published to the client, in the order
+    /// they were sent.
+    fn published(receiver: &Receiver<Message>) -> Vec<PublishDiagnosticsParams> {
+        receiver
+            .try_iter()
+            .filter_map(|msg| match msg {
+                Message::Notification(notification) if notification.method == PublishDiagnostics::METHOD => {
+                    serde_json::from_value(notification.params).ok()
+                }
+                _ => None,
+            })
+            .collect()
+    }
 
-    Range::new(start, end)
-}
+    fn error(message: &str) -> Diagnostic {
+        Diagnostic {
+            severity: Some(DiagnosticSeverity::ERROR),
+            message: message.to_owned(),
+            ..Default::default()
+        }
+    }
 
-#[allow(clippy::cast_possible_truncation)]
-fn position_from_index(text: &str, index: usize) -> Position {
-    let mut line = 0;
-    let mut line_start = 0;
+    #[test]
+    fn fixed_files_are_cleared_once() {
+        let (mut state, receiver) = test_state();
+        let uri = Uri::from_str("file:///project/main.lm").unwrap();
 
-    for (i, b) in text.bytes().enumerate() {
-        if i == index {
-            return Position::new(line, (i - line_start) as u32);
-        }
+        // Simulate a check which published an error to the file.
+        state
+            .error_files_curr
+            .write()
+            .unwrap()
+            .insert(uri.clone(), vec![error("expected `;`")]);
+
+        // The error has been fixed, so the next check raises no diagnostics.
+        state.compile_workspace();
+
+        let cleared = published(&receiver);
+        assert_eq!(cleared.len(), 1);
+        assert_eq!(cleared[0].uri, uri);
+        assert!(cleared[0].diagnostics.is_empty());
 
-        if b == b'\n' {
-            line += 1;
-            line_start = i + 1;
+        // Files are only cleared once, not on every subsequent check.
+        state.compile_workspace();
+
+        assert!(published(&receiver).is_empty());
+    }
+
+    #[test]
+    fn unused_codes_are_unnecessary() {
+        for code in UNNECESSARY_CODES {
+            assert_eq!(tags_of_code(code), Some(vec![DiagnosticTag::UNNECESSARY]));
         }
     }
 
-    Position::new(line, (index - line_start) as u32)
+    #[test]
+    fn deprecated_codes_are_deprecated() {
+        assert_eq!(tags_of_code("deprecated"), Some(vec![DiagnosticTag::DEPRECATED]));
+    }
+
+    #[test]
+    fn other_codes_have_no_tags() {
+        assert_eq!(tags_of_code("type-mismatch"), None);
+        assert_eq!(tags_of_code("unused"), None);
+        assert_eq!(tags_of_code(""), None);
+    }
+
+    /// Checks a workspace with the given roots and files, returning all
+    /// diagnostics which were published to the given file.
+    ///
+    /// Checking a real workspace ties the codes of [`UNNECESSARY_CODES`] to
+    /// what the compiler actually raises, lowered through
+    /// `lower_diagnostic`.
+    fn checked_diagnostics_of(roots: &[&str], files: &[(&str, &str)], file: &str) -> Vec<Diagnostic> {
+        let (_state, receiver, workspace) = checked_test_state(roots, files);
+        let uri = workspace.uri_of(file);
+
+        published(&receiver)
+            .into_iter()
+            .filter(|publish| publish.uri == uri)
+            .flat_map(|publish| publish.diagnostics)
+            .collect()
+    }
+
+    fn with_code<'a>(diagnostics: &'a [Diagnostic], code: &str) -> &'a Diagnostic {
+        diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.code == Some(NumberOrString::String(code.to_owned())))
+            .unwrap_or_else(|| panic!("compiler should raise `{code}`, got {diagnostics:?}"))
+    }
+
+    #[test]
+    fn unused_variable_round_trips_with_tag() {
+        let diagnostics = checked_diagnostics_of(
+            &[""],
+            &[
+                ("Arcfile", &test_arcfile("app", "")),
+                ("src/main.lm", "fn main() {\n    let unused = 1;\n}\n"),
+            ],
+            "src/main.lm",
+        );
+
+        let diagnostic = with_code(&diagnostics, UNUSED_VARIABLE_CODE);
+        assert_eq!(diagnostic.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+        let json = serde_json::to_value(diagnostic).unwrap();
+        assert_eq!(json["tags"], serde_json::json!([1]));
+
+        let round_tripped: Diagnostic = serde_json::from_value(json).unwrap();
+        assert_eq!(&round_tripped, diagnostic);
+    }
+
+    #[test]
+    fn unreachable_code_is_unnecessary() {
+        let diagnostics = checked_diagnostics_of(
+            &[""],
+            &[
+                ("Arcfile", &test_arcfile("app", "")),
+                ("src/main.lm", "fn main() {\n    return;\n    let after = 1;\n}\n"),
+            ],
+            "src/main.lm",
+        );
+
+        let diagnostic = with_code(&diagnostics, "unreachable-code");
+        assert_eq!(diagnostic.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    }
+
+    #[test]
+    fn unused_import_is_unnecessary() {
+        let diagnostics = checked_diagnostics_of(
+            &["app"],
+            &[
+                ("lib/Arcfile", &test_arcfile("lib", "")),
+                ("lib/src/lib.lm", "pub fn one() -> Int32 {\n    return 1;\n}\n"),
+                ("app/Arcfile", &test_arcfile("app", "\"lib\": { path: \"../lib\" }")),
+                ("app/src/main.lm", "import lib (one)\n\nfn main() {}\n"),
+            ],
+            "app/src/main.lm",
+        );
+
+        let diagnostic = with_code(&diagnostics, "unused-import");
+        assert_eq!(diagnostic.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    }
+
+    #[test]
+    fn diagnostics_are_published_once_per_file() {
+        let (state, receiver) = test_state();
+        let main = Uri::from_str("file:///project/main.lm").unwrap();
+        let other = Uri::from_str("file:///project/other.lm").unwrap();
+
+        state.publish_per_file(vec![
+            (main.clone(), error("expected `;`")),
+            (other.clone(), error("unknown type `Foo`")),
+            (main.clone(), error("unknown variable `a`")),
+        ]);
+
+        let publishes = published(&receiver);
+        assert_eq!(publishes.len(), 2);
+
+        assert_eq!(publishes[0].uri, main);
+        assert_eq!(publishes[0].diagnostics, vec![
+            error("expected `;`"),
+            error("unknown variable `a`")
+        ]);
+
+        assert_eq!(publishes[1].uri, other);
+        assert_eq!(publishes[1].diagnostics, vec![error("unknown type `Foo`")]);
+    }
+
+    #[test]
+    fn later_flushes_keep_earlier_diagnostics() {
+        let (state, receiver) = test_state();
+        let main = Uri::from_str("file:///project/main.lm").unwrap();
+
+        state.publish_per_file(vec![(main.clone(), error("expected `;`"))]);
+        state.publish_per_file(vec![(main.clone(), error("unknown variable `a`"))]);
+
+        let publishes = published(&receiver);
+        assert_eq!(publishes.last().unwrap().diagnostics, vec![
+            error("expected `;`"),
+            error("unknown variable `a`")
+        ]);
+    }
 }
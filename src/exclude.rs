@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Filter of paths within the workspace whose documents are never passed to
+/// the compiler and whose diagnostics are never reported, such as build output
+/// or vendored dependencies.
+///
+/// The filter isn't applied when the compiler discovers the sources of a
+/// package on disk, so excluded files which are part of a package are still
+/// compiled, but diagnostics within them are dropped.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExcludeFilter {
+    /// Patterns from the configuration, which always exclude matching paths.
+    set: GlobSet,
+
+    /// Patterns from the `.gitignore` file, in the order they're declared.
+    gitignore: GlobSet,
+
+    /// Whether each pattern within [`ExcludeFilter::gitignore`] is negated,
+    /// meaning it includes matching paths again.
+    negated: Vec<bool>,
+}
+
+impl ExcludeFilter {
+    /// Builds a new filter from the given glob patterns, which are relative to
+    /// the given workspace root.
+    ///
+    /// If `respect_gitignore` is `true`, the patterns within the `.gitignore`
+    /// file at the root of the workspace are excluded as well. Invalid patterns
+    /// are logged and skipped.
+    pub fn new(root: &Path, patterns: &[String], respect_gitignore: bool) -> Self {
+        let gitignore = if respect_gitignore {
+            std::fs::read_to_string(root.join(".gitignore")).unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        Self::from_patterns(patterns, &gitignore)
+    }
+
+    /// Builds a new filter from the given glob patterns and the content of a
+    /// `.gitignore` file.
+    fn from_patterns(patterns: &[String], gitignore: &str) -> Self {
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(err) => log::warn!("invalid exclusion pattern `{pattern}`: {err}"),
+            }
+        }
+
+        let mut gitignore_builder = GlobSetBuilder::new();
+        let mut negated = Vec::new();
+
+        for (glob, is_negated) in gitignore_globs(gitignore) {
+            // Separators must be matched literally, so `*` never matches across
+            // directories, like within `.gitignore` files.
+            match GlobBuilder::new(&glob).literal_separator(true).build() {
+                Ok(glob) => {
+                    gitignore_builder.add(glob);
+                    negated.push(is_negated);
+                }
+                Err(err) => log::warn!("invalid `.gitignore` pattern `{glob}`: {err}"),
+            }
+        }
+
+        Self {
+            set: build_set(&builder),
+            gitignore: build_set(&gitignore_builder),
+            negated,
+        }
+    }
+
+    /// Determines whether the given path, relative to the workspace root, is
+    /// excluded.
+    ///
+    /// Like within `.gitignore` files, the last matching pattern decides
+    /// whether the path is excluded, so negated patterns can include paths
+    /// which were excluded by earlier patterns.
+    pub fn is_excluded(&self, relative_path: &Path) -> bool {
+        if self.set.is_match(relative_path) {
+            return true;
+        }
+
+        self.gitignore
+            .matches(relative_path)
+            .into_iter()
+            .max()
+            .is_some_and(|idx| !self.negated[idx])
+    }
+}
+
+fn build_set(builder: &GlobSetBuilder) -> GlobSet {
+    match builder.build() {
+        Ok(set) => set,
+        Err(err) => {
+            log::warn!("could not build exclusion filter: {err}");
+            GlobSet::empty()
+        }
+    }
+}
+
+/// Converts the patterns within a `.gitignore` file into equivalent globs,
+/// along with whether each glob is negated.
+///
+/// Patterns which contain a slash before their end are anchored to the root of
+/// the workspace, whereas other patterns match at any depth. Patterns which end
+/// with a slash only match directories, which is approximated by only matching
+/// the contents of the directory.
+fn gitignore_globs(content: &str) -> Vec<(String, bool)> {
+    let mut globs = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (line, is_negated) = match line.strip_prefix('!') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let is_directory = line.ends_with('/');
+        let pattern = line.trim_end_matches('/');
+
+        if pattern.is_empty() {
+            continue;
+        }
+
+        let pattern = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_owned()
+        } else {
+            format!("**/{pattern}")
+        };
+
+        globs.push((format!("{pattern}/**"), is_negated));
+
+        if !is_directory {
+            globs.push((pattern, is_negated));
+        }
+    }
+
+    globs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(patterns: &[&str], gitignore: &str) -> ExcludeFilter {
+        let patterns = patterns.iter().map(|pattern| (*pattern).to_owned()).collect::<Vec<_>>();
+
+        ExcludeFilter::from_patterns(&patterns, gitignore)
+    }
+
+    #[test]
+    fn configured_patterns_exclude() {
+        let filter = filter(&["target/**", "**/*.gen.lm"], "");
+
+        assert!(filter.is_excluded(Path::new("target/debug/main.lm")));
+        assert!(filter.is_excluded(Path::new("src/ast.gen.lm")));
+        assert!(!filter.is_excluded(Path::new("src/main.lm")));
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        let filter = filter(&[], "vendor\n");
+
+        assert!(filter.is_excluded(Path::new("vendor/lib.lm")));
+        assert!(filter.is_excluded(Path::new("src/vendor/lib.lm")));
+        assert!(!filter.is_excluded(Path::new("src/vendored.lm")));
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_root() {
+        let filter = filter(&[], "/build\ndocs/*.lm\n");
+
+        assert!(filter.is_excluded(Path::new("build/out.lm")));
+        assert!(!filter.is_excluded(Path::new("src/build/out.lm")));
+
+        assert!(filter.is_excluded(Path::new("docs/example.lm")));
+        assert!(!filter.is_excluded(Path::new("docs/nested/example.lm")));
+        assert!(!filter.is_excluded(Path::new("src/docs/example.lm")));
+    }
+
+    #[test]
+    fn directory_patterns_only_match_contents() {
+        let filter = filter(&[], "out/\n");
+
+        assert!(filter.is_excluded(Path::new("out/main.lm")));
+        assert!(!filter.is_excluded(Path::new("out")));
+    }
+
+    #[test]
+    fn negated_patterns_include_again() {
+        let filter = filter(&[], "*.lm\n!keep.lm\n");
+
+        assert!(filter.is_excluded(Path::new("src/main.lm")));
+        assert!(!filter.is_excluded(Path::new("src/keep.lm")));
+    }
+
+    #[test]
+    fn later_patterns_take_precedence() {
+        let filter = filter(&[], "!keep.lm\n*.lm\n");
+
+        assert!(filter.is_excluded(Path::new("src/keep.lm")));
+    }
+
+    #[test]
+    fn negation_does_not_override_configured_patterns() {
+        let filter = filter(&["**/keep.lm"], "!keep.lm\n");
+
+        assert!(filter.is_excluded(Path::new("src/keep.lm")));
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let filter = filter(&[], "# target\n\n   \n");
+
+        assert!(!filter.is_excluded(Path::new("target/main.lm")));
+    }
+}
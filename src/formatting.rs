@@ -0,0 +1,327 @@
+use lsp_types::{FormattingOptions, Position, Range, TextEdit};
+use lume_errors::Result;
+use lume_span::{Location, SourceFile};
+
+use crate::position::{index_from_position, position_from_index};
+use crate::state::State;
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Formats the given source text, according to the given options.
+///
+/// Formatting is purely whitespace-based: each line is re-indented according
+/// to the nesting depth of brackets at the start of the line, trailing
+/// whitespace is removed and consecutive blank lines are collapsed into one.
+/// The content of multi-line string literals is left untouched.
+///
+/// If the brackets within the source are not balanced, or a string literal is
+/// left unterminated, the source is most likely in the middle of being edited
+/// and [`None`] is returned, so the buffer isn't mangled.
+pub(crate) fn format_source(source: &str, options: &FormattingOptions) -> Option<String> {
+    let lines = source.split('\n').collect::<Vec<_>>();
+    let formatted = format_lines(&lines, 0, lines.len(), options)?;
+
+    let mut output = formatted.join("\n");
+
+    if options.trim_final_newlines.unwrap_or(true) {
+        let trimmed_len = output.trim_end_matches('\n').len();
+        output.truncate(trimmed_len);
+    }
+
+    if options.insert_final_newline.unwrap_or(true) && !output.ends_with('\n') {
+        output.push('\n');
+    }
+
+    Some(output)
+}
+
+/// Formats the lines which span the given range within the given file.
+///
+/// If the start or end of the range lies within a statement, the range is
+/// expanded to include the entirety of the innermost such statement. Returns
+/// [`None`] if the formatted lines are identical to the source, or if the
+/// source is not balanced.
+pub(crate) fn format_range(
+    state: &State,
+    file: &SourceFile,
+    range: Range,
+    options: &FormattingOptions,
+) -> Result<Option<TextEdit>> {
+    let content = file.content.as_str();
+    let encoding = state.position_encoding;
+
+    let mut start = index_from_position(
+        content,
+        range.start.line as usize,
+        range.start.character as usize,
+        encoding,
+    );
+
+    let mut end = index_from_position(content, range.end.line as usize, range.end.character as usize, encoding);
+
+    if let Some(package) = state.checked.graph.packages.get(&file.package) {
+        let mut visitor = StatementVisitor {
+            file,
+            statements: Vec::new(),
+        };
+
+        traverse(package.tcx.hir(), &mut visitor)?;
+
+        if let Some(stmt) = innermost_statement(&visitor.statements, start) {
+            start = start.min(stmt.index.start);
+        }
+
+        if let Some(stmt) = innermost_statement(&visitor.statements, end) {
+            end = end.max(stmt.index.end);
+        }
+    }
+
+    // Expand the range to the start of the first line and the end of the last line.
+    let line_start = content[..start].rfind('\n').map_or(0, |idx| idx + 1);
+    let line_end = content[end..].find('\n').map_or(content.len(), |idx| end + idx);
+
+    let first_line = content[..line_start].matches('\n').count();
+    let last_line = first_line + content[line_start..line_end].matches('\n').count();
+
+    let lines = content.split('\n').collect::<Vec<_>>();
+    let Some(formatted) = format_lines(&lines, first_line, last_line + 1, options) else {
+        return Ok(None);
+    };
+
+    let new_text = formatted.join("\n");
+    if new_text == content[line_start..line_end] {
+        return Ok(None);
+    }
+
+    Ok(Some(TextEdit {
+        range: Range {
+            start: position_from_index(content, line_start, encoding),
+            end: position_from_index(content, line_end, encoding),
+        },
+        new_text,
+    }))
+}
+
+/// Re-indents the lines affected by typing the given character at the end of
+/// the given line, returning an edit for each line whose indentation changed.
+///
+/// Typing `}` re-indents the block which was just closed, from the line after
+/// its opening bracket up to and including the closing bracket. Typing a line
+/// break re-indents the new line only. Only the leading whitespace of lines is
+/// changed, so expressions are never reflowed.
+///
+/// If the source is not balanced after typing `}`, no edits are returned.
+pub(crate) fn format_on_type(content: &str, line: usize, ch: &str, options: &FormattingOptions) -> Vec<TextEdit> {
+    let lines = content.split('\n').collect::<Vec<_>>();
+
+    let Some((depths, is_balanced)) = line_depths(&lines) else {
+        return Vec::new();
+    };
+
+    let Some(Some(line_depth)) = depths.get(line).copied() else {
+        return Vec::new();
+    };
+
+    let affected_lines = match ch {
+        "}" if is_balanced => {
+            // The opening line of the block is the closest preceding line at the same
+            // depth, since all lines within the block are nested deeper.
+            let block_start = (0..line)
+                .rev()
+                .find(|idx| depths[*idx].is_some_and(|depth| depth <= line_depth) && !lines[*idx].trim().is_empty())
+                .map_or(0, |idx| idx + 1);
+
+            block_start..=line
+        }
+        "\n" => line..=line,
+        _ => return Vec::new(),
+    };
+
+    let indent_unit = if options.insert_spaces {
+        " ".repeat(options.tab_size as usize)
+    } else {
+        String::from("\t")
+    };
+
+    let mut edits = Vec::new();
+
+    for idx in affected_lines {
+        let Some(depth) = depths[idx] else {
+            continue;
+        };
+
+        let text = lines[idx].strip_suffix('\r').unwrap_or(lines[idx]);
+
+        // Blank lines within the block are left alone, apart from the new line
+        // itself, where the cursor is placed after the indentation.
+        if text.trim().is_empty() && ch == "}" {
+            continue;
+        }
+
+        let current = &text[..text.len() - text.trim_start().len()];
+        let indentation = indent_unit.repeat(depth);
+
+        if current == indentation {
+            continue;
+        }
+
+        let line_number = u32::try_from(idx).unwrap_or(u32::MAX);
+        let current_len = u32::try_from(current.len()).unwrap_or(u32::MAX);
+
+        edits.push(TextEdit {
+            range: Range::new(Position::new(line_number, 0), Position::new(line_number, current_len)),
+            new_text: indentation,
+        });
+    }
+
+    edits
+}
+
+/// Gets the nesting depth of each line within the given source, at which it
+/// should be indented. Lines which start within a multi-line string literal
+/// have no depth, since they must be left untouched.
+///
+/// Returns [`None`] if a closing bracket has no matching opening bracket, and
+/// otherwise, whether all brackets and strings are closed at the end.
+fn line_depths(lines: &[&str]) -> Option<(Vec<Option<usize>>, bool)> {
+    let mut scanner = Scanner::default();
+    let mut depths = Vec::with_capacity(lines.len());
+
+    for line in lines {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        if scanner.in_string {
+            depths.push(None);
+        } else {
+            let closing = line
+                .trim()
+                .chars()
+                .take_while(|ch| matches!(ch, '}' | ')' | ']'))
+                .count();
+
+            depths.push(Some(scanner.depth.saturating_sub(closing)));
+        }
+
+        scanner.scan_line(line)?;
+    }
+
+    Some((depths, scanner.depth == 0 && !scanner.in_string))
+}
+
+/// Finds the smallest statement which contains the given index.
+fn innermost_statement(statements: &[Location], index: usize) -> Option<&Location> {
+    statements
+        .iter()
+        .filter(|stmt| stmt.index.start < index && stmt.index.end > index)
+        .min_by_key(|stmt| stmt.index.len())
+}
+
+/// Re-indents the lines within `start..end`, while tracking the nesting depth
+/// of all lines in the source, so the balance of the entire source can be
+/// verified.
+fn format_lines(lines: &[&str], start: usize, end: usize, options: &FormattingOptions) -> Option<Vec<String>> {
+    let indent_unit = if options.insert_spaces {
+        " ".repeat(options.tab_size as usize)
+    } else {
+        String::from("\t")
+    };
+
+    let mut scanner = Scanner::default();
+    let mut formatted = Vec::with_capacity(end.saturating_sub(start));
+
+    for (idx, line) in lines.iter().enumerate() {
+        let in_range = idx >= start && idx < end;
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        if in_range {
+            let trimmed = line.trim();
+
+            if scanner.in_string {
+                formatted.push(line.to_owned());
+            } else if trimmed.is_empty() {
+                // Collapse consecutive blank lines into a single one.
+                if formatted.last().is_none_or(|prev: &String| !prev.is_empty()) {
+                    formatted.push(String::new());
+                }
+            } else {
+                let closing = trimmed.chars().take_while(|ch| matches!(ch, '}' | ')' | ']')).count();
+                let depth = scanner.depth.saturating_sub(closing);
+
+                formatted.push(format!("{}{trimmed}", indent_unit.repeat(depth)));
+            }
+        }
+
+        scanner.scan_line(line)?;
+    }
+
+    if scanner.depth != 0 || scanner.in_string {
+        return None;
+    }
+
+    Some(formatted)
+}
+
+/// Tracks the nesting depth of brackets across lines, while ignoring brackets
+/// within string literals and comments.
+#[derive(Default)]
+struct Scanner {
+    depth: usize,
+    in_string: bool,
+    in_block_comment: bool,
+}
+
+impl Scanner {
+    /// Scans a single line of source text, returning [`None`] if a closing
+    /// bracket is found with no matching opening bracket.
+    fn scan_line(&mut self, line: &str) -> Option<()> {
+        let mut chars = line.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            if self.in_block_comment {
+                if ch == '*' && chars.next_if_eq(&'/').is_some() {
+                    self.in_block_comment = false;
+                }
+
+                continue;
+            }
+
+            if self.in_string {
+                match ch {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            match ch {
+                '"' => self.in_string = true,
+                '/' if chars.next_if_eq(&'/').is_some() => break,
+                '/' if chars.next_if_eq(&'*').is_some() => self.in_block_comment = true,
+                '{' | '(' | '[' => self.depth += 1,
+                '}' | ')' | ']' => self.depth = self.depth.checked_sub(1)?,
+                _ => {}
+            }
+        }
+
+        Some(())
+    }
+}
+
+/// Collects the locations of all statements within a single file.
+struct StatementVisitor<'a> {
+    file: &'a SourceFile,
+    statements: Vec<Location>,
+}
+
+impl Visitor for StatementVisitor<'_> {
+    fn visit_stmt(&mut self, stmt: &lume_hir::Statement) -> Result<()> {
+        if stmt.location.file.id == self.file.id {
+            self.statements.push(stmt.location);
+        }
+
+        Ok(())
+    }
+}
@@ -1,41 +1,44 @@
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use lsp_types::*;
-use lume_span::SourceFile;
+use lume_span::{FileName, PackageId, SourceFile};
 
+use crate::config::Config;
+use crate::exclude::ExcludeFilter;
 use crate::state::State;
+use crate::uri::{path_from_uri, uri_from_path};
 
 pub(crate) fn open_document(state: &mut State, params: DidOpenTextDocumentParams) {
+    if !state.is_tracked(&params.text_document.uri) {
+        log::debug!("ignoring untracked document {}", params.text_document.uri.as_str());
+        return;
+    }
+
     log::info!("added document {}", params.text_document.uri.as_str());
 
     let uri = &params.text_document.uri;
 
-    let Some(source_file) = state.source_of_uri(&uri) else {
+    let Some(source_file) = state.source_of_uri(uri) else {
         // If we don't currently have a current workspace, try to locate the
         // workspace root by iterating the parent directories of the newly-opened file.
-        if state.checked.graph.packages.is_empty() {
-            let mut iter_path = PathBuf::from(uri.path().as_str());
-
-            while let Some(directory) = iter_path.parent() {
-                if !directory.join("Arcfile").exists() {
-                    iter_path = directory.to_path_buf();
-                    continue;
-                }
-
-                let workspace_root = format!("file://{}", directory.to_str().unwrap());
-                state.vfs.workspace_root = Uri::from_str(&workspace_root).unwrap();
-                state.compile_workspace();
-
-                // If we actually found any packages, try to run the handler again.
-                if !state.checked.graph.packages.is_empty() {
-                    return open_document(state, params);
-                }
-            }
+        if state.checked.graph.packages.is_empty() && locate_workspace_root(state, uri) {
+            return open_document(state, params);
         }
 
-        log::error!("could not find any matching package");
+        // Files outside of any package cannot be checked, but the server should
+        // stay alive so it can serve other documents.
+        log::warn!("could not find any package containing {}", uri.as_str());
+
+        state.show_message(
+            MessageType::WARNING,
+            &format!(
+                "{} is not part of any package with an Arcfile, so only syntax errors will be reported",
+                uri.path()
+            ),
+        );
+
+        open_standalone_document(state, params.text_document);
         return;
     };
 
@@ -54,6 +57,65 @@ pub(crate) fn open_document(state: &mut State, params: DidOpenTextDocumentParams
     state.compile_workspace();
 }
 
+/// Adds the given document to the VFS without any package, so it's still
+/// synchronized with the client and its syntax errors are reported.
+///
+/// Once the document is discovered as part of a package, it is mapped onto the
+/// source file of the package when checking the workspace.
+fn open_standalone_document(state: &mut State, document: TextDocumentItem) {
+    let TextDocumentItem { uri, text, .. } = document;
+
+    let source_file = SourceFile::new(PackageId::empty(), FileName::Real(path_from_uri(&uri)), text);
+    state.vfs.add_document(uri.clone(), Arc::new(source_file));
+
+    state.check_syntax(&uri);
+}
+
+/// Attempts to find the root of the workspace which contains the given
+/// document, from the closest parent directory with an `Arcfile`.
+///
+/// Returns `true` if a workspace with one-or-more packages was found.
+/// Otherwise, the original workspace roots are restored.
+fn locate_workspace_root(state: &mut State, uri: &Uri) -> bool {
+    let file_path = path_from_uri(uri);
+
+    // Only the closest package can contain the document, so checking any further
+    // ancestors would only cost a full check each.
+    let Some(directory) = file_path
+        .ancestors()
+        .skip(1)
+        .find(|directory| directory.join("Arcfile").exists())
+    else {
+        return false;
+    };
+
+    let Some(workspace_root) = uri_from_path(&directory.join("")) else {
+        log::warn!("could not create workspace URI from {}", directory.display());
+        return false;
+    };
+
+    let original_roots = state.workspace_root_uris();
+
+    state.set_workspace_roots(vec![workspace_root]);
+    state.compile_workspace();
+
+    if state.checked.graph.packages.is_empty() {
+        state.set_workspace_roots(original_roots);
+        return false;
+    }
+
+    true
+}
+
+/// Determines whether the document with the given URI is open without being
+/// part of any package, in which case it can only be checked for syntax errors.
+fn is_standalone(state: &State, uri: &Uri) -> bool {
+    state
+        .vfs
+        .get_document(uri)
+        .is_some_and(|document| document.file.package == PackageId::empty())
+}
+
 pub(crate) fn close_document(state: &mut State, params: DidCloseTextDocumentParams) {
     log::info!("removed document {}", params.text_document.uri.as_str());
 
@@ -63,20 +125,287 @@ pub(crate) fn close_document(state: &mut State, params: DidCloseTextDocumentPara
 }
 
 pub(crate) fn save_document(state: &mut State, params: DidSaveTextDocumentParams) {
+    // The `Arcfile` is always read from disk, so changes to it only take effect
+    // once it has been saved.
+    if is_arcfile(&params.text_document.uri) && !state.is_excluded(&params.text_document.uri) {
+        log::info!("updated arcfile {} (via save)", params.text_document.uri.as_str());
+
+        reload_workspace(state);
+        return;
+    }
+
+    if !state.is_tracked(&params.text_document.uri) {
+        log::debug!("ignoring untracked document {}", params.text_document.uri.as_str());
+        return;
+    }
+
     log::info!("updated document {} (via save)", params.text_document.uri.as_str());
 
-    state
-        .vfs
-        .change_document(&params.text_document.uri, params.text.unwrap());
+    // Not all clients honor `include_text`, in which case the in-memory content is
+    // kept, since it's already synchronized through change notifications. Unopened
+    // documents are read from disk when checking.
+    match params.text {
+        Some(text) => state.vfs.change_document(&params.text_document.uri, text),
+        None => log::debug!(
+            "save of {} had no text, keeping in-memory content",
+            params.text_document.uri.as_str()
+        ),
+    }
 
-    state.compile_workspace();
+    // Standalone documents don't belong to any root, so checking the workspace
+    // wouldn't report anything for them.
+    if is_standalone(state, &params.text_document.uri) {
+        state.check_syntax(&params.text_document.uri);
+        return;
+    }
+
+    state.compile_affected_by(&params.text_document.uri);
 }
 
 pub(crate) fn change_document(state: &mut State, params: DidChangeTextDocumentParams) {
+    if !state.is_tracked(&params.text_document.uri) {
+        log::debug!("ignoring untracked document {}", params.text_document.uri.as_str());
+        return;
+    }
+
+    // Some clients send empty changes as a no-op, which don't warrant a new check.
+    if params.content_changes.is_empty() {
+        log::debug!("ignoring empty change of {}", params.text_document.uri.as_str());
+        return;
+    }
+
     log::info!("updated document {} (via change)", params.text_document.uri.as_str());
 
-    let source = params.content_changes.first().unwrap().text.clone();
-    state.vfs.change_document(&params.text_document.uri, source);
+    state.vfs.apply_changes(
+        &params.text_document.uri,
+        params.content_changes,
+        state.position_encoding,
+    );
+
+    // Type checking the workspace is expensive and fails anyway while the document
+    // doesn't parse, so syntax errors are reported first, without a full check.
+    // Meanwhile, semantic features are served from the last successful check.
+    //
+    // Standalone documents don't belong to any root, so a check of the workspace
+    // wouldn't report anything for them.
+    if state.check_syntax(&params.text_document.uri) && !is_standalone(state, &params.text_document.uri) {
+        state.compile_affected_by(&params.text_document.uri);
+    }
+}
+
+pub(crate) fn change_watched_files(state: &mut State, params: DidChangeWatchedFilesParams) {
+    let mut has_changes = false;
+    let mut has_arcfile_changes = false;
+
+    for event in params.changes {
+        if is_arcfile(&event.uri) && !state.is_excluded(&event.uri) {
+            has_arcfile_changes = true;
+        } else if !state.is_tracked(&event.uri) {
+            continue;
+        }
+
+        log::info!("file {} changed on disk", event.uri.as_str());
+
+        // Files which were created or changed are read from disk when checking, unless
+        // they're open in the editor, whose content takes precedence. Deleted files
+        // must be evicted, so they aren't checked from their stale content.
+        if event.typ == FileChangeType::DELETED {
+            state.vfs.remove_document(&event.uri);
+        }
+
+        has_changes = true;
+    }
+
+    if has_arcfile_changes {
+        reload_workspace(state);
+    } else if has_changes {
+        state.compile_workspace();
+    }
+}
+
+pub(crate) fn change_configuration(state: &mut State, params: DidChangeConfigurationParams) {
+    // Clients which support pulling the configuration usually send no settings
+    // within the notification, so the configuration must be fetched instead.
+    if state.configuration_support {
+        state.request_configuration();
+        return;
+    }
+
+    state.update_config(Config::from_settings(&params.settings));
+}
+
+pub(crate) fn change_workspace_folders(state: &mut State, params: DidChangeWorkspaceFoldersParams) {
+    let mut roots = state.workspace_root_uris();
+
+    for folder in params.event.removed {
+        log::info!("removed workspace folder {}", folder.uri.as_str());
 
+        // Roots which were found within the folder are removed along with it.
+        let folder_path = path_from_uri(&folder.uri);
+        roots.retain(|root| !path_from_uri(root).starts_with(&folder_path));
+    }
+
+    for folder in params.event.added {
+        log::info!("added workspace folder {}", folder.uri.as_str());
+
+        let folder_path = path_from_uri(&folder.uri);
+
+        // The folder itself might not be a package, but instead contain one-or-more
+        // packages within its subdirectories, such as within a monorepo.
+        let package_roots = if folder_path.join("Arcfile").exists() {
+            crate::ensure_trailing_slash(folder).into_iter().collect()
+        } else {
+            let exclude = ExcludeFilter::new(&folder_path, &state.config.exclude, state.config.respect_gitignore);
+
+            package_roots_in(&folder_path, &folder_path, &exclude)
+                .iter()
+                .filter_map(|directory| uri_from_path(&directory.join("")))
+                .collect()
+        };
+
+        if package_roots.is_empty() {
+            log::warn!("could not find any package within {}", folder_path.display());
+        }
+
+        for root in package_roots {
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+    }
+
+    state.set_workspace_roots(roots);
+
+    // Files within removed folders aren't checked anymore, so their diagnostics are
+    // cleared when draining, since they no longer have any.
     state.compile_workspace();
 }
+
+/// Finds all directories within the given directory which contain an
+/// `Arcfile`. Subdirectories of packages aren't searched, since nested
+/// packages are loaded as part of their parent.
+///
+/// Hidden and excluded directories are skipped.
+fn package_roots_in(directory: &Path, folder: &Path, exclude: &ExcludeFilter) -> Vec<PathBuf> {
+    if directory.join("Arcfile").exists() {
+        return vec![directory.to_path_buf()];
+    }
+
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    let mut roots = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let is_excluded = path.strip_prefix(folder).is_ok_and(|path| exclude.is_excluded(path));
+
+        if path.is_dir() && !is_hidden && !is_excluded {
+            roots.extend(package_roots_in(&path, folder, exclude));
+        }
+    }
+
+    roots
+}
+
+/// Determines whether the given document is an `Arcfile`, which declares the
+/// metadata and dependencies of a package.
+fn is_arcfile(uri: &Uri) -> bool {
+    uri.path().as_str().ends_with("/Arcfile")
+}
+
+/// Rebuilds the package graph of the workspace from scratch, after any of the
+/// `Arcfile`s within the workspace has changed.
+///
+/// Since the dependencies of a package might have changed without any of its
+/// sources changing, no cached symbols are reused.
+fn reload_workspace(state: &mut State) {
+    state.checked.clear_symbol_cache();
+    state.compile_workspace();
+
+    if state.checked.is_stale {
+        state.show_message(
+            MessageType::WARNING,
+            "Changes to the Arcfile could not be applied, since the workspace failed to load",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::tests::{open_test_document, test_state};
+
+    #[test]
+    fn empty_change_list_is_ignored() {
+        let (mut state, receiver) = test_state();
+        let uri = open_test_document(&mut state, "file:///project/main.lm", "let a = 1;");
+
+        change_document(&mut state, DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: 2,
+            },
+            content_changes: Vec::<TextDocumentContentChangeEvent>::new(),
+        });
+
+        assert_eq!(state.vfs.get_document(&uri).unwrap().file.content, "let a = 1;");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn save_without_text_keeps_content() {
+        let (mut state, _) = test_state();
+        let uri = open_test_document(&mut state, "file:///project/main.lm", "let a = 1;");
+
+        save_document(&mut state, DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            text: None,
+        });
+
+        assert_eq!(state.vfs.get_document(&uri).unwrap().file.content, "let a = 1;");
+    }
+
+    #[test]
+    fn save_with_text_replaces_content() {
+        let (mut state, _) = test_state();
+        let uri = open_test_document(&mut state, "file:///project/main.lm", "let a = 1;");
+
+        save_document(&mut state, DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            text: Some(String::from("let b = 2;")),
+        });
+
+        assert_eq!(state.vfs.get_document(&uri).unwrap().file.content, "let b = 2;");
+    }
+
+    #[test]
+    fn standalone_changes_only_check_syntax() {
+        let (mut state, _) = test_state();
+        let uri = open_test_document(&mut state, "file:///scratch/main.lm", "fn main() {}");
+        let version = state.checked.version;
+
+        change_document(&mut state, DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: 2,
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: String::from("fn main() { }"),
+            }],
+        });
+
+        save_document(&mut state, DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri },
+            text: None,
+        });
+
+        // Every check of the workspace replaces the graph, even without any roots.
+        assert_eq!(state.checked.version, version);
+    }
+}
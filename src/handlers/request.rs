@@ -1,5 +1,5 @@
 use lsp_server::RequestId;
-use lsp_types::{Hover, HoverContents, HoverParams, MarkupContent, Position};
+use lsp_types::*;
 use lume_errors::Result;
 
 use crate::state::State;
@@ -13,8 +13,8 @@ pub(crate) fn on_hover(state: &State, id: RequestId, params: HoverParams) -> Res
         return Ok(());
     };
 
-    let content = match crate::symbols::hover::hover_content_of(state, location) {
-        Ok(content) => content,
+    let hover = match crate::symbols::hover::hover_content_of(state, location) {
+        Ok(hover) => hover,
         Err(err) => {
             log::error!("could not retrieve content: {}", err.message());
 
@@ -27,13 +27,942 @@ pub(crate) fn on_hover(state: &State, id: RequestId, params: HoverParams) -> Res
         }
     };
 
-    state.ok(id, &Hover {
+    let hover = hover.map(|(content, span)| Hover {
         contents: HoverContents::Markup(MarkupContent {
-            kind: lsp_types::MarkupKind::Markdown,
+            kind: state.hover_content_format.clone(),
             value: content,
         }),
-        range: None,
-    })?;
+        range: Some(state.lsp_range_of(span)),
+    });
+
+    state.ok(id, &hover)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_goto_definition(state: &State, id: RequestId, params: GotoDefinitionParams) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let definition = match crate::symbols::definition::definition_of(state, location) {
+        Ok(definition) => definition,
+        Err(err) => {
+            log::error!("could not resolve definition: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not resolve definition: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    let response = definition
+        .and_then(|loc| state.lsp_location_of(loc))
+        .map(GotoDefinitionResponse::Scalar);
+    state.ok(id, &response)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_goto_type_definition(state: &State, id: RequestId, params: GotoDefinitionParams) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let definition = match crate::symbols::definition::type_definition_of(state, location) {
+        Ok(definition) => definition,
+        Err(err) => {
+            log::error!("could not resolve type definition: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not resolve type definition: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    let response = definition
+        .and_then(|loc| state.lsp_location_of(loc))
+        .map(GotoDefinitionResponse::Scalar);
+    state.ok(id, &response)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_goto_implementation(state: &State, id: RequestId, params: GotoDefinitionParams) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let implementations = match crate::symbols::implementation::implementations_of(state, location) {
+        Ok(implementations) => implementations,
+        Err(err) => {
+            log::error!("could not resolve implementations: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not resolve implementations: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    let locations = implementations
+        .into_iter()
+        .filter_map(|loc| state.lsp_location_of(loc))
+        .collect::<Vec<_>>();
+
+    state.ok(id, &GotoDefinitionResponse::Array(locations))?;
+
+    Ok(())
+}
+
+pub(crate) fn on_references(state: &State, id: RequestId, params: ReferenceParams) -> Result<()> {
+    let uri = &params.text_document_position.text_document.uri;
+    let Position { line, character } = params.text_document_position.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let include_declaration = params.context.include_declaration;
+
+    let references = match crate::symbols::references::references_of(state, location, include_declaration) {
+        Ok(references) => references,
+        Err(err) => {
+            log::error!("could not find references: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not find references: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    let locations = references
+        .into_iter()
+        .filter_map(|loc| state.lsp_location_of(loc))
+        .collect::<Vec<_>>();
+
+    state.ok(id, &locations)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_document_symbol(state: &State, id: RequestId, params: DocumentSymbolParams) -> Result<()> {
+    let uri = &params.text_document.uri;
+
+    let Some(source_file) = state.source_of_uri(uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let Some(package) = state.checked.graph.packages.get(&source_file.package) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let hir = package.tcx.hir();
+
+    let symbols = match crate::symbols::outline::document_symbols_of(hir, &source_file, state.position_encoding) {
+        Ok(symbols) => symbols,
+        Err(err) => {
+            log::error!("could not build document symbols: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not build document symbols: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &DocumentSymbolResponse::Nested(symbols))?;
+
+    Ok(())
+}
+
+pub(crate) fn on_workspace_symbol(state: &State, id: RequestId, params: WorkspaceSymbolParams) -> Result<()> {
+    let symbols = match crate::symbols::workspace::workspace_symbols(state, &params.query) {
+        Ok(symbols) => symbols,
+        Err(err) => {
+            log::error!("could not search workspace symbols: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not search workspace symbols: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &WorkspaceSymbolResponse::Nested(symbols))?;
+
+    Ok(())
+}
+
+pub(crate) fn on_completion(state: &State, id: RequestId, params: CompletionParams) -> Result<()> {
+    let uri = &params.text_document_position.text_document.uri;
+    let Position { line, character } = params.text_document_position.position;
+
+    let trigger_character = params
+        .context
+        .as_ref()
+        .and_then(|context| context.trigger_character.as_deref());
+
+    let items =
+        crate::symbols::completion::completions_at(state, uri, line as usize, character as usize, trigger_character);
+
+    state.ok(id, &CompletionResponse::Array(items))?;
+
+    Ok(())
+}
+
+pub(crate) fn on_signature_help(state: &State, id: RequestId, params: SignatureHelpParams) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let help = crate::symbols::signature::signature_help_at(state, uri, line as usize, character as usize);
+
+    state.ok(id, &help)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_semantic_tokens_full(state: &State, id: RequestId, params: SemanticTokensParams) -> Result<()> {
+    let Some(source_file) = state.source_of_uri(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let data = crate::symbols::semantic::semantic_tokens_of(state, &source_file);
+
+    state.ok(
+        id,
+        &SemanticTokensResult::Tokens(SemanticTokens { result_id: None, data }),
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn on_rename(state: &State, id: RequestId, params: RenameParams) -> Result<()> {
+    let uri = &params.text_document_position.text_document.uri;
+    let Position { line, character } = params.text_document_position.position;
+
+    if !crate::symbols::rename::is_valid_identifier(&params.new_name) {
+        state.err(
+            id,
+            lsp_server::ErrorCode::InvalidParams,
+            &format!("`{}` is not a valid identifier", params.new_name),
+        )?;
+        return Ok(());
+    }
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let edit = match crate::symbols::rename::rename_edits(state, location, &params.new_name) {
+        Ok(edit) => edit,
+        Err(err) => {
+            log::error!("could not rename symbol: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not rename symbol: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &edit)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_prepare_rename(state: &State, id: RequestId, params: TextDocumentPositionParams) -> Result<()> {
+    let uri = &params.text_document.uri;
+    let Position { line, character } = params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let target = match crate::symbols::rename::prepare_rename(state, location) {
+        Ok(target) => target,
+        Err(err) => {
+            log::error!("could not prepare rename: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not prepare rename: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    let response = target.map(|loc| PrepareRenameResponse::Range(state.lsp_range_of(loc)));
+    state.ok(id, &response)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_document_highlight(state: &State, id: RequestId, params: DocumentHighlightParams) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let highlights = match crate::symbols::references::document_highlights(state, location) {
+        Ok(highlights) => highlights,
+        Err(err) => {
+            log::error!("could not find highlights: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not find highlights: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &highlights)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_inlay_hint(state: &State, id: RequestId, params: InlayHintParams) -> Result<()> {
+    let Some(source_file) = state.source_of_uri(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let hints = match crate::symbols::inlay::inlay_hints_of(state, &source_file, params.range) {
+        Ok(hints) => hints,
+        Err(err) => {
+            log::error!("could not compute inlay hints: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not compute inlay hints: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &hints)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_formatting(state: &State, id: RequestId, params: DocumentFormattingParams) -> Result<()> {
+    let Some(document) = state.vfs.get_document(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let content = document.file.content.as_str();
+
+    let Some(formatted) = crate::formatting::format_source(content, &params.options) else {
+        log::debug!(
+            "skipped formatting of unbalanced document {}",
+            params.text_document.uri.as_str()
+        );
+
+        state.ok(id, &Vec::<TextEdit>::new())?;
+        return Ok(());
+    };
+
+    let edits = if formatted == content {
+        Vec::new()
+    } else {
+        vec![TextEdit {
+            range: Range {
+                start: Position::new(0, 0),
+                end: crate::position::position_from_index(content, content.len(), state.position_encoding),
+            },
+            new_text: formatted,
+        }]
+    };
+
+    state.ok(id, &edits)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_range_formatting(state: &State, id: RequestId, params: DocumentRangeFormattingParams) -> Result<()> {
+    let Some(source_file) = state.source_of_uri(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let edit = match crate::formatting::format_range(state, &source_file, params.range, &params.options) {
+        Ok(edit) => edit,
+        Err(err) => {
+            log::error!("could not format range: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not format range: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &edit.into_iter().collect::<Vec<_>>())?;
+
+    Ok(())
+}
+
+pub(crate) fn on_folding_range(state: &State, id: RequestId, params: FoldingRangeParams) -> Result<()> {
+    let Some(source_file) = state.source_of_uri(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let Some(package) = state.checked.graph.packages.get(&source_file.package) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let ranges =
+        match crate::symbols::folding::folding_ranges_of(package.tcx.hir(), &source_file, state.position_encoding) {
+            Ok(ranges) => ranges,
+            Err(err) => {
+                log::error!("could not build folding ranges: {}", err.message());
+
+                state.err(
+                    id,
+                    lsp_server::ErrorCode::RequestFailed,
+                    &format!("could not build folding ranges: {}", err.message()),
+                )?;
+                return Ok(());
+            }
+        };
+
+    state.ok(id, &ranges)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_selection_range(state: &State, id: RequestId, params: SelectionRangeParams) -> Result<()> {
+    let Some(source_file) = state.source_of_uri(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let Some(package) = state.checked.graph.packages.get(&source_file.package) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let ranges = match crate::symbols::selection::selection_ranges_of(
+        package.tcx.hir(),
+        &source_file,
+        &params.positions,
+        state.position_encoding,
+    ) {
+        Ok(ranges) => ranges,
+        Err(err) => {
+            log::error!("could not build selection ranges: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not build selection ranges: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &ranges)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_call_hierarchy_prepare(
+    state: &State,
+    id: RequestId,
+    params: CallHierarchyPrepareParams,
+) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let item = match crate::symbols::calls::prepare_call_hierarchy(state, location) {
+        Ok(item) => item,
+        Err(err) => {
+            log::error!("could not prepare call hierarchy: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not prepare call hierarchy: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &item.map(|item| vec![item]))?;
+
+    Ok(())
+}
+
+pub(crate) fn on_call_hierarchy_incoming(
+    state: &State,
+    id: RequestId,
+    params: CallHierarchyIncomingCallsParams,
+) -> Result<()> {
+    let Position { line, character } = params.item.selection_range.start;
+
+    let Some(location) = state.location_of(&params.item.uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let calls = match crate::symbols::calls::incoming_calls(state, location) {
+        Ok(calls) => calls,
+        Err(err) => {
+            log::error!("could not find incoming calls: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not find incoming calls: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &calls)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_call_hierarchy_outgoing(
+    state: &State,
+    id: RequestId,
+    params: CallHierarchyOutgoingCallsParams,
+) -> Result<()> {
+    let Position { line, character } = params.item.selection_range.start;
+
+    let Some(location) = state.location_of(&params.item.uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let calls = match crate::symbols::calls::outgoing_calls(state, location) {
+        Ok(calls) => calls,
+        Err(err) => {
+            log::error!("could not find outgoing calls: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not find outgoing calls: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &calls)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_type_hierarchy_prepare(
+    state: &State,
+    id: RequestId,
+    params: TypeHierarchyPrepareParams,
+) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let item = crate::symbols::type_hierarchy::prepare_type_hierarchy(state, location);
+    state.ok(id, &item.map(|item| vec![item]))?;
+
+    Ok(())
+}
+
+pub(crate) fn on_type_hierarchy_supertypes(
+    state: &State,
+    id: RequestId,
+    params: TypeHierarchySupertypesParams,
+) -> Result<()> {
+    let Position { line, character } = params.item.selection_range.start;
+
+    let Some(location) = state.location_of(&params.item.uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let supertypes = crate::symbols::type_hierarchy::supertypes_of(state, location);
+    state.ok(id, &supertypes)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_type_hierarchy_subtypes(
+    state: &State,
+    id: RequestId,
+    params: TypeHierarchySubtypesParams,
+) -> Result<()> {
+    let Position { line, character } = params.item.selection_range.start;
+
+    let Some(location) = state.location_of(&params.item.uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let subtypes = crate::symbols::type_hierarchy::subtypes_of(state, location);
+    state.ok(id, &subtypes)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_code_action(state: &State, id: RequestId, params: CodeActionParams) -> Result<()> {
+    let uri = &params.text_document.uri;
+
+    let Some(source_file) = state.source_of_uri(uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let diagnostics = &params.context.diagnostics;
+
+    let actions = match crate::symbols::actions::code_actions_for(state, uri, &source_file, diagnostics) {
+        Ok(actions) => actions,
+        Err(err) => {
+            log::error!("could not build code actions: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not build code actions: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &actions)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_execute_command(state: &mut State, id: RequestId, params: ExecuteCommandParams) -> Result<()> {
+    match params.command.as_str() {
+        crate::COMMAND_RECHECK_WORKSPACE => {
+            log::info!("rechecking workspace (via command)");
+
+            state.checked.clear_symbol_cache();
+            state.compile_workspace();
+
+            state.ok(id, &serde_json::Value::Null)?;
+        }
+        crate::COMMAND_EXPORT_DIAGNOSTICS => {
+            state.ok(id, &state.export_diagnostics())?;
+        }
+        crate::COMMAND_OPEN_ARCFILE => {
+            let uri = params
+                .arguments
+                .first()
+                .and_then(|argument| serde_json::from_value::<Uri>(argument.clone()).ok());
+
+            state.ok(id, &state.arcfile_of(uri.as_ref()))?;
+        }
+        crate::COMMAND_DUMP_HIR if state.config.debug_features => {
+            let Some(uri) = params
+                .arguments
+                .first()
+                .and_then(|argument| serde_json::from_value::<Uri>(argument.clone()).ok())
+            else {
+                state.err(id, lsp_server::ErrorCode::InvalidParams, "expected document URI")?;
+                return Ok(());
+            };
+
+            let Some(dump) = crate::debug::dump_hir_of(state, &uri) else {
+                state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+                return Ok(());
+            };
+
+            state.ok(id, &dump)?;
+        }
+        command => {
+            state.err(
+                id,
+                lsp_server::ErrorCode::InvalidParams,
+                &format!("unknown command: {command}"),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn on_document_color(state: &State, id: RequestId, params: DocumentColorParams) -> Result<()> {
+    let Some(source_file) = state.source_of_uri(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let colors = crate::symbols::color::document_colors(state, &source_file);
+
+    state.ok(id, &colors)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_color_presentation(state: &State, id: RequestId, params: ColorPresentationParams) -> Result<()> {
+    let presentations = crate::symbols::color::color_presentations(params.color, params.range);
+
+    state.ok(id, &presentations)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_linked_editing_range(state: &State, id: RequestId, params: LinkedEditingRangeParams) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let ranges = match crate::symbols::rename::linked_editing_ranges(state, location) {
+        Ok(ranges) => ranges,
+        Err(err) => {
+            log::error!("could not resolve linked editing ranges: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not resolve linked editing ranges: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &ranges)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_moniker(state: &State, id: RequestId, params: MonikerParams) -> Result<()> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let Position { line, character } = params.text_document_position_params.position;
+
+    let Some(location) = state.location_of(uri, line as usize, character as usize) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let moniker = match crate::symbols::moniker::moniker_of(state, location) {
+        Ok(moniker) => moniker,
+        Err(err) => {
+            log::error!("could not resolve moniker: {}", err.message());
+
+            state.err(
+                id,
+                lsp_server::ErrorCode::RequestFailed,
+                &format!("could not resolve moniker: {}", err.message()),
+            )?;
+            return Ok(());
+        }
+    };
+
+    state.ok(id, &moniker.map(|moniker| vec![moniker]))?;
+
+    Ok(())
+}
+
+pub(crate) fn on_type_formatting(state: &State, id: RequestId, params: DocumentOnTypeFormattingParams) -> Result<()> {
+    let Some(document) = state.vfs.get_document(&params.text_document_position.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let edits = crate::formatting::format_on_type(
+        &document.file.content,
+        params.text_document_position.position.line as usize,
+        &params.ch,
+        &params.options,
+    );
+
+    state.ok(id, &edits)?;
+
+    Ok(())
+}
+
+pub(crate) fn on_semantic_tokens_range(state: &State, id: RequestId, params: SemanticTokensRangeParams) -> Result<()> {
+    let Some(source_file) = state.source_of_uri(&params.text_document.uri) else {
+        state.err(id, lsp_server::ErrorCode::InvalidParams, "document not available")?;
+        return Ok(());
+    };
+
+    let data = crate::symbols::semantic::semantic_tokens_in_range(state, &source_file, params.range);
+
+    state.ok(
+        id,
+        &SemanticTokensRangeResult::Tokens(SemanticTokens { result_id: None, data }),
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn on_document_diagnostic(state: &State, id: RequestId, params: DocumentDiagnosticParams) -> Result<()> {
+    let items = state.diagnostics_of_file(&params.text_document.uri);
+    let result_id = State::result_id_of(&items);
+
+    let report = if params.previous_result_id.as_ref() == Some(&result_id) {
+        DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+            related_documents: None,
+            unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport { result_id },
+        })
+    } else {
+        DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(result_id),
+                items,
+            },
+        })
+    };
+
+    state.ok(id, &DocumentDiagnosticReportResult::Report(report))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crossbeam::channel::Receiver;
+    use lsp_server::Message;
+
+    use super::*;
+    use crate::state::tests::test_state;
+
+    /// Pulls the diagnostics of the given document, returning the report which
+    /// was sent to the client.
+    fn pull(
+        state: &State,
+        receiver: &Receiver<Message>,
+        uri: &Uri,
+        previous_result_id: Option<String>,
+    ) -> DocumentDiagnosticReport {
+        let params = DocumentDiagnosticParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            identifier: None,
+            previous_result_id,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        on_document_diagnostic(state, RequestId::from(1), params).unwrap();
+
+        let Ok(Message::Response(response)) = receiver.try_recv() else {
+            panic!("expected a response to the pull");
+        };
+
+        match serde_json::from_value(response.result.unwrap()).unwrap() {
+            DocumentDiagnosticReportResult::Report(report) => report,
+            DocumentDiagnosticReportResult::Partial(_) => panic!("expected a full report"),
+        }
+    }
+
+    #[test]
+    fn repeated_pulls_are_unchanged() {
+        let (state, receiver) = test_state();
+        let uri = Uri::from_str("file:///project/main.lm").unwrap();
+
+        state
+            .error_files_curr
+            .write()
+            .unwrap()
+            .insert(uri.clone(), vec![Diagnostic {
+                message: String::from("expected `;`"),
+                ..Default::default()
+            }]);
+
+        let DocumentDiagnosticReport::Full(first) = pull(&state, &receiver, &uri, None) else {
+            panic!("expected a full report on the first pull");
+        };
+
+        let result_id = first.full_document_diagnostic_report.result_id.unwrap();
+        assert_eq!(first.full_document_diagnostic_report.items.len(), 1);
+
+        let DocumentDiagnosticReport::Unchanged(second) = pull(&state, &receiver, &uri, Some(result_id.clone())) else {
+            panic!("expected an unchanged report on the second pull");
+        };
+
+        assert_eq!(second.unchanged_document_diagnostic_report.result_id, result_id);
+    }
+
+    #[test]
+    fn changed_diagnostics_are_sent_in_full() {
+        let (state, receiver) = test_state();
+        let uri = Uri::from_str("file:///project/main.lm").unwrap();
+
+        let DocumentDiagnosticReport::Full(first) = pull(&state, &receiver, &uri, None) else {
+            panic!("expected a full report on the first pull");
+        };
+
+        let result_id = first.full_document_diagnostic_report.result_id.unwrap();
+
+        state
+            .error_files_curr
+            .write()
+            .unwrap()
+            .insert(uri.clone(), vec![Diagnostic {
+                message: String::from("expected `;`"),
+                ..Default::default()
+            }]);
+
+        let report = pull(&state, &receiver, &uri, Some(result_id));
+        assert!(matches!(report, DocumentDiagnosticReport::Full(_)));
+    }
+}
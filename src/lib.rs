@@ -1,20 +1,52 @@
+use std::cell::{Cell, RefCell};
 use std::error::Error;
 use std::str::FromStr;
 
 use lsp_server::Connection;
+use lsp_types::notification::Notification as _;
 use lsp_types::*;
 use lume_errors::{Result, SimpleDiagnostic};
 
+pub use crate::check::check_workspace;
+use crate::config::Config;
+use crate::position::PositionEncoding;
 use crate::state::State;
 
+pub(crate) mod cancel;
+pub(crate) mod check;
+pub(crate) mod config;
+pub(crate) mod debug;
 pub(crate) mod diagnostics;
+pub(crate) mod exclude;
+pub(crate) mod formatting;
 pub(crate) mod listen;
+pub(crate) mod position;
+pub(crate) mod progress;
 pub(crate) mod state;
+pub(crate) mod uri;
+pub(crate) mod watch;
 
 mod symbols {
+    pub(crate) mod actions;
+    pub(crate) mod calls;
+    pub(crate) mod color;
+    pub(crate) mod completion;
+    pub(crate) mod definition;
+    pub(crate) mod folding;
     pub(crate) mod hover;
+    pub(crate) mod implementation;
+    pub(crate) mod inlay;
     pub(crate) mod lookup;
+    pub(crate) mod moniker;
+    pub(crate) mod outline;
+    pub(crate) mod references;
+    pub(crate) mod rename;
+    pub(crate) mod selection;
+    pub(crate) mod semantic;
+    pub(crate) mod signature;
+    pub(crate) mod type_hierarchy;
     pub(crate) mod visitor;
+    pub(crate) mod workspace;
 }
 
 mod handlers {
@@ -22,20 +54,94 @@ mod handlers {
     pub(crate) mod request;
 }
 
-pub fn start_server() -> std::result::Result<(), Box<dyn Error + Sync + Send>> {
-    let (conn, io) = Connection::stdio();
-    let capabilities = capabilities();
+/// Command which discards all cached state and checks the entire workspace
+/// again.
+pub(crate) const COMMAND_RECHECK_WORKSPACE: &str = "lume.recheckWorkspace";
+
+/// Command which returns all current diagnostics within the workspace as a
+/// JSON array, for tooling integrations.
+pub(crate) const COMMAND_EXPORT_DIAGNOSTICS: &str = "lume.exportDiagnostics";
+
+/// Command which returns the URI of the `Arcfile` of the package containing
+/// the given document, for quickly navigating to it.
+pub(crate) const COMMAND_OPEN_ARCFILE: &str = "lume.openArcfile";
+
+/// Command which renders the HIR of a single document, for debugging the
+/// language server. Only available if debug features are enabled.
+pub(crate) const COMMAND_DUMP_HIR: &str = "lume.dumpHir";
+
+/// Transport over which the server communicates with the language client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// Communicate over standard input and output.
+    #[default]
+    Stdio,
+
+    /// Listen on the given TCP address and communicate with the first client
+    /// which connects to it.
+    Socket(String),
+
+    /// Connect to a client which is listening on the given TCP address.
+    Connect(String),
+}
+
+pub fn start_server(transport: Transport) -> std::result::Result<(), Box<dyn Error + Sync + Send>> {
+    let (conn, io) = match transport {
+        Transport::Stdio => Connection::stdio(),
+        Transport::Socket(addr) => {
+            log::info!("waiting for client to connect on {addr}");
+
+            Connection::listen(addr.as_str())?
+        }
+        Transport::Connect(addr) => {
+            log::info!("connecting to client on {addr}");
+
+            Connection::connect(addr.as_str())?
+        }
+    };
 
     log::info!("starting up!");
 
-    let params_json = conn.initialize(serde_json::json!(capabilities))?;
-    let params = serde_json::from_value(params_json)?;
+    let (init_id, params_json) = conn.initialize_start()?;
+    let params: InitializeParams = serde_json::from_value(params_json)?;
+
+    let position_encoding = PositionEncoding::negotiate(&params);
+    let config = Config::from_options(params.initialization_options.as_ref());
+
+    let capabilities = advertised_capabilities_with(&config, Some(position_encoding.kind()))?;
+
+    let server_info = ServerInfo {
+        name: String::from("lume-lsp"),
+        version: Some(String::from(env!("CARGO_PKG_VERSION"))),
+    };
+
+    conn.initialize_finish(
+        init_id,
+        serde_json::json!({ "capabilities": capabilities, "serverInfo": server_info }),
+    )?;
+
+    let panic_sender = conn.sender.clone();
 
-    std::panic::set_hook(Box::new(|panic_info| {
-        if let Some(payload) = panic_info.payload_as_str() {
-            log::error!("LSP server panicked: {payload}");
+    std::panic::set_hook(Box::new(move |panic_info| {
+        let payload = panic_info.payload_as_str().unwrap_or("<no payload>");
+        log::error!("LSP server panicked: {payload}");
+
+        // Panics within `catch_panic` are recovered from, so they're reported by the
+        // caller instead of being shown as an internal error.
+        if CATCHING_PANIC.get() {
+            CAUGHT_PAYLOAD.set(Some(payload.to_owned()));
         } else {
-            log::error!("LSP server panicked: <no payload>");
+            // Since the panic might leave the server in a broken state, the user should
+            // be made aware of it, instead of having features silently stop working.
+            let params = ShowMessageParams {
+                typ: MessageType::ERROR,
+                message: format!("Lume language server encountered an internal error: {payload}"),
+            };
+
+            let _ = panic_sender.send(lsp_server::Message::Notification(lsp_server::Notification::new(
+                notification::ShowMessage::METHOD.to_owned(),
+                params,
+            )));
         }
 
         if let Some(location) = panic_info.location() {
@@ -47,7 +153,7 @@ pub fn start_server() -> std::result::Result<(), Box<dyn Error + Sync + Send>> {
         }
     }));
 
-    if let Err(err) = initialize(conn, params) {
+    if let Err(err) = initialize(conn, params, position_encoding, config) {
         return Err(Box::new(std::io::Error::other(err.message())));
     }
 
@@ -57,43 +163,295 @@ pub fn start_server() -> std::result::Result<(), Box<dyn Error + Sync + Send>> {
     Ok(())
 }
 
+/// Gets the capabilities of the server, which is the single source of truth for
+/// all features advertised to the client.
+///
+/// Capabilities which have no field within [`ServerCapabilities`] are only
+/// included within [`advertised_capabilities`].
 pub fn capabilities() -> ServerCapabilities {
     ServerCapabilities {
+        call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+            resolve_provider: Some(false),
+        })),
+        color_provider: Some(ColorProviderCapability::Simple(true)),
         completion_provider: Some(CompletionOptions {
             resolve_provider: Some(false),
+            trigger_characters: Some(vec![String::from("."), String::from(":")]),
             ..Default::default()
         }),
+        definition_provider: Some(OneOf::Left(true)),
+        diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
+            identifier: Some(String::from("lume")),
+            inter_file_dependencies: true,
+            workspace_diagnostics: false,
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+        })),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_highlight_provider: Some(OneOf::Left(true)),
+        document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+            first_trigger_character: String::from("}"),
+            more_trigger_character: Some(vec![String::from("\n")]),
+        }),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![
+                String::from(COMMAND_RECHECK_WORKSPACE),
+                String::from(COMMAND_EXPORT_DIAGNOSTICS),
+                String::from(COMMAND_OPEN_ARCFILE),
+            ],
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+        }),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
         hover_provider: Some(HoverProviderCapability::Simple(true)),
+        implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
+        inlay_hint_provider: Some(OneOf::Left(true)),
+        linked_editing_range_provider: Some(LinkedEditingRangeServerCapabilities::Simple(true)),
+        moniker_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Right(RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+        })),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: symbols::semantic::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                range: Some(true),
+                ..Default::default()
+            },
+        )),
+        signature_help_provider: Some(SignatureHelpOptions {
+            trigger_characters: Some(vec![String::from("("), String::from(",")]),
+            ..Default::default()
+        }),
         text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
             open_close: Some(true),
-            change: Some(TextDocumentSyncKind::FULL),
+            change: Some(TextDocumentSyncKind::INCREMENTAL),
             save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
                 include_text: Some(true),
             })),
             ..Default::default()
         })),
+        type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
+        workspace: Some(WorkspaceServerCapabilities {
+            workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                supported: Some(true),
+                change_notifications: Some(OneOf::Left(true)),
+            }),
+            file_operations: None,
+        }),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
         ..Default::default()
     }
 }
 
-fn initialize(connection: Connection, mut params: InitializeParams) -> Result<()> {
-    let Some(workspace_root) = params.workspace_folders.take().map(|mut folders| folders.remove(0)) else {
+/// Gets the capabilities of the server, serialized as they are sent to the
+/// client during initialization, using the given position encoding.
+///
+/// # Errors
+///
+/// Returns `Err` if the capabilities could not be serialized.
+pub fn advertised_capabilities(
+    position_encoding: Option<PositionEncodingKind>,
+) -> serde_json::Result<serde_json::Value> {
+    advertised_capabilities_with(&Config::default(), position_encoding)
+}
+
+/// Gets the capabilities of the server, like [`advertised_capabilities`], but
+/// without the features which are disabled within the given configuration.
+fn advertised_capabilities_with(
+    config: &Config,
+    position_encoding: Option<PositionEncodingKind>,
+) -> serde_json::Result<serde_json::Value> {
+    let mut capabilities = capabilities();
+    capabilities.position_encoding = position_encoding;
+
+    config.configure_capabilities(&mut capabilities);
+
+    // `lsp-types` has no field for the type hierarchy capability, so it is added
+    // to the serialized capabilities instead.
+    let mut capabilities = serde_json::to_value(capabilities)?;
+    capabilities["typeHierarchyProvider"] = serde_json::Value::Bool(true);
+
+    Ok(capabilities)
+}
+
+fn initialize(
+    connection: Connection,
+    mut params: InitializeParams,
+    position_encoding: PositionEncoding,
+    config: Config,
+) -> Result<()> {
+    let workspace_roots = params
+        .workspace_folders
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(ensure_trailing_slash)
+        .collect::<Vec<Uri>>();
+
+    if workspace_roots.is_empty() {
         return Err(SimpleDiagnostic::new("no workspace root defined").into());
-    };
+    }
+
+    let mut state = State::new(connection.sender, workspace_roots, position_encoding, config);
+
+    state.work_done_progress = params
+        .capabilities
+        .window
+        .as_ref()
+        .and_then(|window| window.work_done_progress)
+        .unwrap_or(false);
+
+    let watch_files = params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+        .and_then(|watched_files| watched_files.dynamic_registration)
+        .unwrap_or(false);
 
-    let workspace_root = ensure_trailing_slash(workspace_root);
+    if watch_files {
+        state.register_file_watchers();
+    }
+
+    if let Some(workspace) = &params.capabilities.workspace {
+        state.configuration_support = workspace.configuration.unwrap_or(false);
+
+        // Without refreshing, the client would only pull diagnostics of the documents
+        // it changes itself, missing diagnostics raised within other documents.
+        state.pull_diagnostics = workspace
+            .diagnostic
+            .as_ref()
+            .and_then(|diagnostic| diagnostic.refresh_support)
+            .unwrap_or(false)
+            && params
+                .capabilities
+                .text_document
+                .as_ref()
+                .is_some_and(|text_document| text_document.diagnostic.is_some());
+
+        state.inlay_hint_refresh_support = workspace
+            .inlay_hint
+            .as_ref()
+            .and_then(|inlay_hint| inlay_hint.refresh_support)
+            .unwrap_or(false);
+    }
+
+    state.snippet_support = params
+        .capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.completion.as_ref())
+        .and_then(|completion| completion.completion_item.as_ref())
+        .and_then(|completion_item| completion_item.snippet_support)
+        .unwrap_or(false);
+
+    // Clients list the formats they support in order of preference, but may
+    // include formats which the server cannot produce.
+    state.hover_content_format = params
+        .capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.hover.as_ref())
+        .and_then(|hover| hover.content_format.as_ref())
+        .and_then(|formats| {
+            formats
+                .iter()
+                .find(|format| matches!(format, MarkupKind::Markdown | MarkupKind::PlainText))
+        })
+        .cloned()
+        .unwrap_or(MarkupKind::Markdown);
+
+    state.request_configuration();
 
-    let mut state = State::new(connection.sender, workspace_root);
     state.compile_workspace();
     state.listen(connection.receiver)
 }
 
-fn ensure_trailing_slash(folder: WorkspaceFolder) -> Uri {
+thread_local! {
+    /// Whether the current thread is within [`catch_panic`].
+    static CATCHING_PANIC: Cell<bool> = const { Cell::new(false) };
+
+    /// Payload of the latest panic which was caught by [`catch_panic`], as
+    /// extracted by the panic hook.
+    static CAUGHT_PAYLOAD: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Invokes the given closure, returning the payload of the panic if it panics.
+///
+/// Unlike other panics, the user isn't notified of the panic by the panic hook,
+/// so the caller can report it instead.
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> T) -> std::result::Result<T, String> {
+    let was_catching = CATCHING_PANIC.replace(true);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    CATCHING_PANIC.set(was_catching);
+
+    result.map_err(|_| CAUGHT_PAYLOAD.take().unwrap_or_else(|| String::from("<no payload>")))
+}
+
+/// Gets the URI of the given workspace folder, ending with a trailing slash so
+/// it can be used as a root to resolve relative paths against.
+///
+/// If the URI cannot be extended, it is rebuilt from its path instead, which
+/// percent-encodes all characters which aren't allowed within a URI. Returns
+/// [`None`] if neither forms a valid URI.
+pub(crate) fn ensure_trailing_slash(folder: WorkspaceFolder) -> Option<Uri> {
     if folder.uri.path().as_str().ends_with('/') {
-        folder.uri.clone()
-    } else {
-        let uri = folder.uri.as_str();
+        return Some(folder.uri);
+    }
+
+    let uri = folder.uri.as_str();
+
+    let root = Uri::from_str(&format!("{uri}/"))
+        .ok()
+        .or_else(|| crate::uri::uri_from_path(&crate::uri::path_from_uri(&folder.uri).join("")));
+
+    if root.is_none() {
+        log::warn!("ignoring workspace folder with invalid URI: {uri}");
+    }
+
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(uri: &str) -> WorkspaceFolder {
+        WorkspaceFolder {
+            uri: Uri::from_str(uri).unwrap(),
+            name: String::from("workspace"),
+        }
+    }
+
+    #[test]
+    fn trailing_slash_is_kept() {
+        let root = ensure_trailing_slash(folder("file:///home/user/project/")).unwrap();
+
+        assert_eq!(root.as_str(), "file:///home/user/project/");
+    }
+
+    #[test]
+    fn trailing_slash_is_appended() {
+        let root = ensure_trailing_slash(folder("file:///home/user/project")).unwrap();
+
+        assert_eq!(root.as_str(), "file:///home/user/project/");
+    }
+
+    #[test]
+    fn trailing_slash_with_encoded_characters() {
+        let root = ensure_trailing_slash(folder("file:///home/user/my%20projects/caf%C3%A9")).unwrap();
 
-        Uri::from_str(&format!("{uri}/")).unwrap()
+        assert_eq!(root.as_str(), "file:///home/user/my%20projects/caf%C3%A9/");
+        assert_eq!(
+            crate::uri::path_from_uri(&root),
+            std::path::PathBuf::from("/home/user/my projects/café/")
+        );
     }
 }
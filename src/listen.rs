@@ -1,8 +1,13 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
 use crossbeam::channel::Receiver;
-use lsp_server::{Message, Response};
+use lsp_server::{ErrorCode, Message, RequestId, Response};
 use lsp_types::request::{Request, Shutdown};
 use lume_errors::{IntoDiagnostic, Result};
+use serde::de::DeserializeOwned;
 
+use crate::config::Config;
 use crate::handlers;
 use crate::state::State;
 
@@ -10,7 +15,17 @@ impl State {
     /// Starts listening on the given [`Connection`] for LSP requests and
     /// notifications.
     pub fn listen(&mut self, receiver: Receiver<Message>) -> Result<()> {
-        for msg in &receiver {
+        let mut queue = VecDeque::new();
+
+        loop {
+            let msg = match queue.pop_front() {
+                Some(msg) => msg,
+                None => match receiver.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                },
+            };
+
             match msg {
                 Message::Request(req) => {
                     if req.method == Shutdown::METHOD {
@@ -22,40 +37,245 @@ impl State {
                         break;
                     }
 
-                    if let Err(err) = self.handle_request(&req) {
-                        log::error!("request {} failed: {err}", &req.method);
+                    self.register_request(&req.id);
+
+                    // Requests are often cancelled while waiting for an earlier check to
+                    // finish, so the cancellation is already queued behind the request.
+                    self.enqueue_pending(&receiver, &mut queue);
+
+                    if self.is_cancelled(&req.id) {
+                        let _ = self.err(req.id.clone(), ErrorCode::RequestCanceled, "request was cancelled");
+                    } else {
+                        let start = Instant::now();
+
+                        if let Err(err) = self.handle_request(&req) {
+                            log::error!("request {} failed: {err}", &req.method);
+                        }
+
+                        log::debug!("{} took {}ms", &req.method, start.elapsed().as_millis());
                     }
+
+                    self.finish_request(&req.id);
                 }
                 Message::Notification(req) => {
+                    let start = Instant::now();
+
                     if let Err(err) = self.handle_notification(&req) {
                         log::error!("notification {} failed: {err}", &req.method);
                     }
+
+                    log::debug!("{} took {}ms", &req.method, start.elapsed().as_millis());
                 }
-                Message::Response(resp) => log::error!("got unexpected response: {resp:?}"),
+                // Responses are only received for requests sent by the server. Most of
+                // them, such as creating progress tokens, require no further handling.
+                Message::Response(resp) => match resp.error {
+                    Some(err) => log::warn!("client responded with error to {:?}: {}", resp.id, err.message),
+                    None if self.take_pending_configuration(&resp.id) => {
+                        self.handle_configuration_response(resp.result);
+                    }
+                    None => log::debug!("got response: {:?}", resp.id),
+                },
             }
         }
 
         Ok(())
     }
 
+    /// Moves all messages which are waiting on the given receiver into the
+    /// given queue, so any cancellations between them are applied before the
+    /// queued requests are handled.
+    fn enqueue_pending(&self, receiver: &Receiver<Message>, queue: &mut VecDeque<Message>) {
+        use lsp_types::notification::{Cancel, Notification};
+
+        for msg in receiver.try_iter() {
+            match &msg {
+                Message::Notification(notification) if notification.method == Cancel::METHOD => {
+                    match parse_params(&notification.params) {
+                        Ok(params) => self.cancel_request(params),
+                        Err(err) => log::error!("notification {} failed: {err}", &notification.method),
+                    }
+
+                    continue;
+                }
+                Message::Request(req) => self.register_request(&req.id),
+                _ => {}
+            }
+
+            queue.push_back(msg);
+        }
+    }
+
     fn handle_request(&mut self, request: &lsp_server::Request) -> Result<()> {
         log::debug!("received request: {}", request.method);
 
         match request.method.as_str() {
+            lsp_types::request::Completion::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_completion(self, request.id.clone(), params)?;
+            }
             lsp_types::request::HoverRequest::METHOD => {
-                let params: lsp_types::HoverParams = match serde_json::from_value(request.params.clone()) {
-                    Ok(params) => params,
-                    Err(err) => return Err(err.into_diagnostic()),
-                };
-
+                let params = parse_params(&request.params)?;
                 handlers::request::on_hover(self, request.id.clone(), params)?;
             }
+            lsp_types::request::GotoDefinition::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_goto_definition(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::References::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_references(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::DocumentSymbolRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_document_symbol(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::WorkspaceSymbolRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_workspace_symbol(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::SignatureHelpRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_signature_help(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::SemanticTokensFullRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_semantic_tokens_full(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::SemanticTokensRangeRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_semantic_tokens_range(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::Rename::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_rename(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::PrepareRenameRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_prepare_rename(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::DocumentHighlightRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_document_highlight(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::InlayHintRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_inlay_hint(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::Formatting::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_formatting(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::RangeFormatting::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_range_formatting(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::FoldingRangeRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_folding_range(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::SelectionRangeRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_selection_range(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::CallHierarchyPrepare::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_call_hierarchy_prepare(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::CallHierarchyIncomingCalls::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_call_hierarchy_incoming(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::CallHierarchyOutgoingCalls::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_call_hierarchy_outgoing(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::TypeHierarchyPrepare::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_type_hierarchy_prepare(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::TypeHierarchySupertypes::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_type_hierarchy_supertypes(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::TypeHierarchySubtypes::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_type_hierarchy_subtypes(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::CodeActionRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_code_action(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::GotoTypeDefinition::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_goto_type_definition(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::GotoImplementation::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_goto_implementation(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::ExecuteCommand::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_execute_command(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::DocumentColor::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_document_color(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::ColorPresentationRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_color_presentation(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::LinkedEditingRange::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_linked_editing_range(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::MonikerRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_moniker(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::OnTypeFormatting::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_type_formatting(self, request.id.clone(), params)?;
+            }
+            lsp_types::request::DocumentDiagnosticRequest::METHOD => {
+                let params = parse_params(&request.params)?;
+                handlers::request::on_document_diagnostic(self, request.id.clone(), params)?;
+            }
             _ => {}
         }
 
         Ok(())
     }
 
+    /// Determines whether the given response ID belongs to the latest pending
+    /// configuration request, in which case it is no longer pending.
+    fn take_pending_configuration(&self, id: &RequestId) -> bool {
+        let mut pending = self.pending_configuration.write().unwrap();
+
+        if pending.as_ref() == Some(id) {
+            *pending = None;
+            return true;
+        }
+
+        false
+    }
+
+    /// Applies the configuration which was fetched from the client, which
+    /// contains a single item for the requested section.
+    fn handle_configuration_response(&mut self, result: Option<serde_json::Value>) {
+        let Some(settings) = result.and_then(|mut items| items.get_mut(0).map(serde_json::Value::take)) else {
+            return;
+        };
+
+        // If the client has no settings for the section, the initialization options
+        // remain in effect.
+        if settings.is_null() {
+            return;
+        }
+
+        self.update_config(Config::from_options(Some(&settings)));
+    }
+
     fn handle_notification(&mut self, notification: &lsp_server::Notification) -> Result<()> {
         use lsp_types::notification::Notification;
 
@@ -63,44 +283,48 @@ impl State {
 
         match notification.method.as_str() {
             lsp_types::notification::DidOpenTextDocument::METHOD => {
-                let params: lsp_types::DidOpenTextDocumentParams =
-                    match serde_json::from_value(notification.params.clone()) {
-                        Ok(params) => params,
-                        Err(err) => return Err(err.into_diagnostic()),
-                    };
-
+                let params = parse_params(&notification.params)?;
                 handlers::notification::open_document(self, params);
             }
             lsp_types::notification::DidCloseTextDocument::METHOD => {
-                let params: lsp_types::DidCloseTextDocumentParams =
-                    match serde_json::from_value(notification.params.clone()) {
-                        Ok(params) => params,
-                        Err(err) => return Err(err.into_diagnostic()),
-                    };
-
+                let params = parse_params(&notification.params)?;
                 handlers::notification::close_document(self, params);
             }
             lsp_types::notification::DidSaveTextDocument::METHOD => {
-                let params: lsp_types::DidSaveTextDocumentParams =
-                    match serde_json::from_value(notification.params.clone()) {
-                        Ok(params) => params,
-                        Err(err) => return Err(err.into_diagnostic()),
-                    };
-
+                let params = parse_params(&notification.params)?;
                 handlers::notification::save_document(self, params);
             }
             lsp_types::notification::DidChangeTextDocument::METHOD => {
-                let params: lsp_types::DidChangeTextDocumentParams =
-                    match serde_json::from_value(notification.params.clone()) {
-                        Ok(params) => params,
-                        Err(err) => return Err(err.into_diagnostic()),
-                    };
-
+                let params = parse_params(&notification.params)?;
                 handlers::notification::change_document(self, params);
             }
+            lsp_types::notification::DidChangeWatchedFiles::METHOD => {
+                let params = parse_params(&notification.params)?;
+                handlers::notification::change_watched_files(self, params);
+            }
+            lsp_types::notification::DidChangeConfiguration::METHOD => {
+                let params = parse_params(&notification.params)?;
+                handlers::notification::change_configuration(self, params);
+            }
+            lsp_types::notification::DidChangeWorkspaceFolders::METHOD => {
+                let params = parse_params(&notification.params)?;
+                handlers::notification::change_workspace_folders(self, params);
+            }
+            lsp_types::notification::Cancel::METHOD => {
+                let params = parse_params(&notification.params)?;
+                self.cancel_request(params);
+            }
             _ => {}
         }
 
         Ok(())
     }
 }
+
+/// Deserializes the given parameters of some request or notification.
+fn parse_params<P: DeserializeOwned>(params: &serde_json::Value) -> Result<P> {
+    match serde_json::from_value(params.clone()) {
+        Ok(params) => Ok(params),
+        Err(err) => Err(err.into_diagnostic()),
+    }
+}
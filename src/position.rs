@@ -0,0 +1,345 @@
+use lsp_types::{InitializeParams, Position, PositionEncodingKind, Range};
+
+/// Defines which unit columns within LSP positions are counted in, as
+/// negotiated with the client during initialization.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PositionEncoding {
+    /// Columns are counted in UTF-8 code units, or bytes.
+    Utf8,
+
+    /// Columns are counted in UTF-16 code units. This is the default encoding
+    /// and must be supported by all clients.
+    #[default]
+    Utf16,
+
+    /// Columns are counted in UTF-32 code units, or characters.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Selects the position encoding to use, from the encodings advertised by
+    /// the client.
+    ///
+    /// UTF-8 is preferred if the client supports it, since it matches the
+    /// byte offsets used internally. Otherwise, the first supported encoding in
+    /// the clients list is used, falling back to UTF-16.
+    pub fn negotiate(params: &InitializeParams) -> Self {
+        let Some(encodings) = params
+            .capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_ref())
+        else {
+            return Self::default();
+        };
+
+        if encodings.contains(&PositionEncodingKind::UTF8) {
+            return Self::Utf8;
+        }
+
+        encodings.iter().find_map(Self::from_kind).unwrap_or_default()
+    }
+
+    /// Converts the given [`PositionEncodingKind`] into a [`PositionEncoding`],
+    /// if it is supported.
+    pub fn from_kind(kind: &PositionEncodingKind) -> Option<Self> {
+        if *kind == PositionEncodingKind::UTF8 {
+            Some(Self::Utf8)
+        } else if *kind == PositionEncodingKind::UTF16 {
+            Some(Self::Utf16)
+        } else if *kind == PositionEncodingKind::UTF32 {
+            Some(Self::Utf32)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the [`PositionEncodingKind`] which is advertised to the client.
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            Self::Utf8 => PositionEncodingKind::UTF8,
+            Self::Utf16 => PositionEncodingKind::UTF16,
+            Self::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// Gets the amount of code units which the given character occupies.
+    fn len_of(self, ch: char) -> usize {
+        match self {
+            Self::Utf8 => ch.len_utf8(),
+            Self::Utf16 => ch.len_utf16(),
+            Self::Utf32 => 1,
+        }
+    }
+}
+
+/// Converts the given [`lume_span::Location`] into an LSP [`Range`] within
+/// the file of the location.
+pub(crate) fn lsp_range_of(location: lume_span::Location, encoding: PositionEncoding) -> Range {
+    position_from_range(&location.file.content, &location.index, encoding)
+}
+
+pub(crate) fn position_from_range(text: &str, range: &std::ops::Range<usize>, encoding: PositionEncoding) -> Range {
+    let start = position_from_index(text, range.start, encoding);
+    let end = position_from_index(text, range.end, encoding);
+
+    Range::new(start, end)
+}
+
+/// Converts the given byte index within `text` into a zero-based line and
+/// column, counted in the given encoding.
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn position_from_index(text: &str, index: usize, encoding: PositionEncoding) -> Position {
+    let mut line = 0;
+    let mut column = 0;
+
+    for (i, ch) in text.char_indices() {
+        if i >= index {
+            break;
+        }
+
+        if ch == '\n' {
+            line += 1;
+            column = 0;
+        } else {
+            column += encoding.len_of(ch) as u32;
+        }
+    }
+
+    Position::new(line, column)
+}
+
+/// Converts the given zero-based line and column, counted in the given
+/// encoding, into a byte index within `text`.
+///
+/// If the column exceeds the length of the line, the index of the end of the
+/// line is returned, before any line terminator, including `\r\n`.
+pub(crate) fn index_from_position(text: &str, line: usize, character: usize, encoding: PositionEncoding) -> usize {
+    let mut line_start = 0;
+
+    for (line_idx, line_str) in text.split_inclusive('\n').enumerate() {
+        if line_idx < line {
+            line_start += line_str.len();
+            continue;
+        }
+
+        let mut units = 0;
+
+        for (byte_idx, ch) in line_str.char_indices() {
+            let is_terminator = ch == '\n' || (ch == '\r' && line_str[byte_idx..].starts_with("\r\n"));
+
+            if units >= character || is_terminator {
+                return line_start + byte_idx;
+            }
+
+            units += encoding.len_of(ch);
+        }
+
+        return line_start + line_str.len();
+    }
+
+    line_start
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{ClientCapabilities, GeneralClientCapabilities};
+
+    use super::*;
+
+    fn params_with_encodings(encodings: Option<Vec<PositionEncodingKind>>) -> InitializeParams {
+        InitializeParams {
+            capabilities: ClientCapabilities {
+                general: Some(GeneralClientCapabilities {
+                    position_encodings: encodings,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn negotiate_defaults_to_utf16() {
+        assert_eq!(
+            PositionEncoding::negotiate(&InitializeParams::default()),
+            PositionEncoding::Utf16
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(&params_with_encodings(None)),
+            PositionEncoding::Utf16
+        );
+    }
+
+    #[test]
+    fn negotiate_prefers_utf8() {
+        let params = params_with_encodings(Some(vec![PositionEncodingKind::UTF16, PositionEncodingKind::UTF8]));
+
+        assert_eq!(PositionEncoding::negotiate(&params), PositionEncoding::Utf8);
+    }
+
+    #[test]
+    fn negotiate_uses_first_supported() {
+        let params = params_with_encodings(Some(vec![
+            PositionEncodingKind::new("utf-7"),
+            PositionEncodingKind::UTF32,
+            PositionEncodingKind::UTF16,
+        ]));
+
+        assert_eq!(PositionEncoding::negotiate(&params), PositionEncoding::Utf32);
+    }
+
+    #[test]
+    fn negotiate_ignores_unsupported() {
+        let params = params_with_encodings(Some(vec![PositionEncodingKind::new("utf-7")]));
+
+        assert_eq!(PositionEncoding::negotiate(&params), PositionEncoding::Utf16);
+    }
+
+    #[test]
+    fn index_of_ascii_position() {
+        let text = "let a = 1;\nlet b = 2;";
+
+        assert_eq!(index_from_position(text, 0, 4, PositionEncoding::Utf16), 4);
+        assert_eq!(index_from_position(text, 1, 4, PositionEncoding::Utf16), 15);
+    }
+
+    #[test]
+    fn index_after_multibyte_character() {
+        // `é` is two bytes in UTF-8, but a single UTF-16 code unit.
+        let text = "let é = 1;";
+
+        assert_eq!(index_from_position(text, 0, 6, PositionEncoding::Utf16), 7);
+        assert_eq!(index_from_position(text, 0, 6, PositionEncoding::Utf32), 7);
+        assert_eq!(index_from_position(text, 0, 7, PositionEncoding::Utf8), 7);
+    }
+
+    #[test]
+    fn index_after_surrogate_pair() {
+        // `😀` is four bytes in UTF-8 and a surrogate pair in UTF-16.
+        let text = "a😀b";
+
+        assert_eq!(index_from_position(text, 0, 3, PositionEncoding::Utf16), 5);
+        assert_eq!(index_from_position(text, 0, 2, PositionEncoding::Utf32), 5);
+        assert_eq!(index_from_position(text, 0, 5, PositionEncoding::Utf8), 5);
+    }
+
+    #[test]
+    fn index_within_surrogate_pair_resolves_past_it() {
+        assert_eq!(index_from_position("a😀b", 0, 2, PositionEncoding::Utf16), 5);
+    }
+
+    #[test]
+    fn index_with_crlf_line_endings() {
+        let text = "ab\r\ncd\r\n";
+
+        assert_eq!(index_from_position(text, 1, 0, PositionEncoding::Utf16), 4);
+        assert_eq!(index_from_position(text, 1, 1, PositionEncoding::Utf16), 5);
+    }
+
+    #[test]
+    fn index_past_end_of_line_is_clamped() {
+        assert_eq!(index_from_position("ab\ncd", 0, 10, PositionEncoding::Utf16), 2);
+        assert_eq!(index_from_position("ab\r\ncd", 0, 10, PositionEncoding::Utf16), 2);
+        assert_eq!(index_from_position("ab", 0, 10, PositionEncoding::Utf16), 2);
+    }
+
+    #[test]
+    fn index_past_last_line_is_end_of_text() {
+        assert_eq!(index_from_position("ab\ncd", 5, 0, PositionEncoding::Utf16), 5);
+        assert_eq!(index_from_position("", 0, 0, PositionEncoding::Utf16), 0);
+    }
+
+    #[test]
+    fn position_of_ascii_index() {
+        let text = "let a = 1;\nlet b = 2;";
+
+        assert_eq!(
+            position_from_index(text, 4, PositionEncoding::Utf16),
+            Position::new(0, 4)
+        );
+        assert_eq!(
+            position_from_index(text, 15, PositionEncoding::Utf16),
+            Position::new(1, 4)
+        );
+    }
+
+    #[test]
+    fn position_after_multibyte_character() {
+        let text = "let é = 1;";
+
+        assert_eq!(
+            position_from_index(text, 7, PositionEncoding::Utf16),
+            Position::new(0, 6)
+        );
+        assert_eq!(
+            position_from_index(text, 7, PositionEncoding::Utf32),
+            Position::new(0, 6)
+        );
+        assert_eq!(
+            position_from_index(text, 7, PositionEncoding::Utf8),
+            Position::new(0, 7)
+        );
+    }
+
+    #[test]
+    fn position_after_surrogate_pair() {
+        let text = "a😀b";
+
+        assert_eq!(
+            position_from_index(text, 5, PositionEncoding::Utf16),
+            Position::new(0, 3)
+        );
+        assert_eq!(
+            position_from_index(text, 5, PositionEncoding::Utf32),
+            Position::new(0, 2)
+        );
+        assert_eq!(
+            position_from_index(text, 5, PositionEncoding::Utf8),
+            Position::new(0, 5)
+        );
+    }
+
+    #[test]
+    fn position_with_crlf_line_endings() {
+        let text = "ab\r\ncd";
+
+        assert_eq!(
+            position_from_index(text, 2, PositionEncoding::Utf16),
+            Position::new(0, 2)
+        );
+        assert_eq!(
+            position_from_index(text, 5, PositionEncoding::Utf16),
+            Position::new(1, 1)
+        );
+    }
+
+    #[test]
+    fn position_past_end_of_text() {
+        assert_eq!(
+            position_from_index("ab\ncd", 100, PositionEncoding::Utf16),
+            Position::new(1, 2)
+        );
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        let text = "fn main() {\r\n    let 😀 = \"é\";\r\n}\n";
+
+        for encoding in [PositionEncoding::Utf8, PositionEncoding::Utf16, PositionEncoding::Utf32] {
+            for (index, _) in text.char_indices() {
+                let position = position_from_index(text, index, encoding);
+                let round_tripped =
+                    index_from_position(text, position.line as usize, position.character as usize, encoding);
+
+                // Indices of `\r` within `\r\n` and `\n` both map to the end of the line.
+                if text[index..].starts_with('\n') && text[..index].ends_with('\r') {
+                    assert_eq!(round_tripped, index - 1);
+                } else {
+                    assert_eq!(round_tripped, index, "{encoding:?} at {index}");
+                }
+            }
+        }
+    }
+}
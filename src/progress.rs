@@ -0,0 +1,91 @@
+use lsp_server::{Message, RequestId};
+use lsp_types::notification::{Notification as _, Progress};
+use lsp_types::request::{Request as _, WorkDoneProgressCreate};
+use lsp_types::*;
+
+use crate::state::State;
+
+impl State {
+    /// Starts reporting a new long-running task to the client, returning the
+    /// token which identifies the task.
+    ///
+    /// If the client doesn't support work-done progress, [`None`] is returned
+    /// and nothing is reported.
+    pub(crate) fn begin_progress(&mut self, title: &str) -> Option<ProgressToken> {
+        if !self.work_done_progress {
+            return None;
+        }
+
+        self.progress_counter += 1;
+
+        let token = ProgressToken::String(format!("lume/progress/{}", self.progress_counter));
+
+        // The token must be created by the client before any progress can be reported
+        // for it. The response is handled in the listening loop.
+        let request = lsp_server::Request::new(
+            RequestId::from(format!("lume/create-progress/{}", self.progress_counter)),
+            WorkDoneProgressCreate::METHOD.to_owned(),
+            WorkDoneProgressCreateParams { token: token.clone() },
+        );
+
+        if let Err(err) = self.dispatcher.send(Message::Request(request)) {
+            log::error!("could not create progress token: {err}");
+            return None;
+        }
+
+        self.send_progress(
+            &token,
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.to_owned(),
+                cancellable: Some(false),
+                message: None,
+                percentage: None,
+            }),
+        );
+
+        Some(token)
+    }
+
+    /// Reports an intermediate message for the task with the given token.
+    pub(crate) fn report_progress(&self, token: Option<&ProgressToken>, message: &str) {
+        let Some(token) = token else {
+            return;
+        };
+
+        self.send_progress(
+            token,
+            WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: Some(false),
+                message: Some(message.to_owned()),
+                percentage: None,
+            }),
+        );
+    }
+
+    /// Reports the task with the given token as finished.
+    pub(crate) fn end_progress(&self, token: Option<ProgressToken>, message: &str) {
+        let Some(token) = token else {
+            return;
+        };
+
+        self.send_progress(
+            &token,
+            WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: Some(message.to_owned()),
+            }),
+        );
+    }
+
+    fn send_progress(&self, token: &ProgressToken, progress: WorkDoneProgress) {
+        let params = ProgressParams {
+            token: token.clone(),
+            value: ProgressParamsValue::WorkDone(progress),
+        };
+
+        let notification = lsp_server::Notification::new(Progress::METHOD.to_owned(), params);
+
+        if let Err(err) = self.dispatcher.send(Message::Notification(notification)) {
+            log::error!("could not report progress: {err}");
+        }
+    }
+}
@@ -1,16 +1,23 @@
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, RwLock};
 
 use crossbeam::channel::Sender;
 use indexmap::IndexMap;
 use lsp_server::*;
-use lsp_types::Uri;
+use lsp_types::notification::{Notification as _, ShowMessage};
+use lsp_types::{Diagnostic, MarkupKind, MessageType, ShowMessageParams, TextDocumentContentChangeEvent, Uri};
 use lume_driver::CheckedPackageGraph;
-use lume_errors::{DiagCtx, IntoDiagnostic, Result};
-use lume_span::{FileName, Internable, Location, SourceFile};
+use lume_errors::{DiagCtx, IntoDiagnostic, Result, SimpleDiagnostic};
+use lume_span::{FileName, Internable, Location, PackageId, SourceFile};
 
-use crate::symbols::lookup::SymbolLookup;
+use crate::config::Config;
+use crate::exclude::ExcludeFilter;
+use crate::position::{PositionEncoding, index_from_position, lsp_range_of};
+use crate::symbols::hover::HoverCache;
+use crate::symbols::lookup::WorkspaceSymbols;
+use crate::uri::{path_from_uri, uri_from_path};
 
 pub(crate) struct State {
     pub dispatcher: Sender<Message>,
@@ -20,58 +27,503 @@ pub(crate) struct State {
     pub checked: CheckedWorkspace,
 
     pub error_files_prev: RwLock<HashSet<Uri>>,
-    pub error_files_curr: RwLock<HashSet<Uri>>,
+
+    /// Diagnostics which have been published during the current check, grouped
+    /// by the file they were published to.
+    pub error_files_curr: RwLock<IndexMap<Uri, Vec<Diagnostic>>>,
 
     pub dcx: DiagCtx,
+
+    pub position_encoding: PositionEncoding,
+
+    pub config: Config,
+
+    /// Root folders of the workspace, which are each checked as their own
+    /// package graph.
+    pub roots: Vec<WorkspaceRoot>,
+
+    /// Whether the client supports server-initiated work-done progress.
+    pub work_done_progress: bool,
+
+    /// Counter used to create unique progress tokens.
+    pub progress_counter: u32,
+
+    /// Counter used to create unique IDs of requests sent to the client.
+    pub request_counter: AtomicU32,
+
+    /// Whether the client supports fetching the configuration through
+    /// `workspace/configuration` requests.
+    pub configuration_support: bool,
+
+    /// Whether the client supports refreshing all inlay hints on request.
+    pub inlay_hint_refresh_support: bool,
+
+    /// Whether the client supports snippets within completion items.
+    pub snippet_support: bool,
+
+    /// Format of hover content which is preferred by the client.
+    pub hover_content_format: MarkupKind,
+
+    /// Whether the client pulls diagnostics and supports refreshing them on
+    /// request, in which case diagnostics are no longer pushed.
+    pub pull_diagnostics: bool,
+
+    /// ID of the latest configuration request sent to the client, which is
+    /// still awaiting a response.
+    pub pending_configuration: RwLock<Option<RequestId>>,
+
+    /// Requests which have been received, but not yet responded to.
+    pub pending_requests: RwLock<HashSet<RequestId>>,
+
+    /// Pending requests which have been cancelled by the client.
+    pub cancelled_requests: RwLock<HashSet<RequestId>>,
 }
 
 impl State {
-    pub fn new(dispatcher: Sender<Message>, root: Uri) -> Self {
+    pub fn new(
+        dispatcher: Sender<Message>,
+        roots: Vec<Uri>,
+        position_encoding: PositionEncoding,
+        config: Config,
+    ) -> Self {
+        let roots = roots
+            .into_iter()
+            .map(|root| WorkspaceRoot::new(root, &config))
+            .collect();
+
         Self {
             dispatcher,
-            vfs: Vfs::new(root),
+            vfs: Vfs::default(),
             checked: CheckedWorkspace::default(),
 
             error_files_prev: RwLock::new(HashSet::new()),
-            error_files_curr: RwLock::new(HashSet::new()),
+            error_files_curr: RwLock::new(IndexMap::new()),
             dcx: DiagCtx::new(),
+
+            position_encoding,
+            config,
+            roots,
+
+            work_done_progress: false,
+            progress_counter: 0,
+            request_counter: AtomicU32::new(0),
+
+            configuration_support: false,
+            inlay_hint_refresh_support: false,
+            snippet_support: false,
+            hover_content_format: MarkupKind::Markdown,
+            pull_diagnostics: false,
+
+            pending_configuration: RwLock::new(None),
+            pending_requests: RwLock::new(HashSet::new()),
+            cancelled_requests: RwLock::new(HashSet::new()),
         }
     }
 
-    /// Checks the current workspace and sends any raised diagnostics to the
-    /// client.
+    /// Checks all roots of the current workspace and sends any raised
+    /// diagnostics to the client.
     pub(crate) fn compile_workspace(&mut self) {
-        log::debug!("compiling workspace at {}", self.vfs.workspace_root.as_str());
+        self.compile_roots(None);
+    }
 
-        std::mem::take(&mut self.error_files_prev);
-        std::mem::swap(&mut self.error_files_prev, &mut self.error_files_curr);
+    /// Checks the workspace after the document with the given URI was edited.
+    ///
+    /// If enabled within the configuration, only roots whose package graph
+    /// contains the package of the document are checked, which includes all
+    /// packages depending on it. Otherwise, or if the package of the document
+    /// isn't known from an earlier, successful check, the entire workspace is
+    /// checked instead.
+    pub(crate) fn compile_affected_by(&mut self, uri: &Uri) {
+        if !self.config.check_affected_roots_only || self.checked.is_stale {
+            self.compile_workspace();
+            return;
+        }
+
+        let Some(package) = self.source_of_uri(uri).map(|source| source.package) else {
+            log::debug!("package of {} is unknown, checking entire workspace", uri.as_str());
+            self.compile_workspace();
+            return;
+        };
+
+        let affected = self.roots_depending_on(package);
+
+        if affected.is_empty() {
+            log::debug!("no root depends on {}, checking entire workspace", uri.as_str());
+            self.compile_workspace();
+            return;
+        }
+
+        self.compile_roots(Some(&affected));
+    }
+
+    /// Gets the paths of all workspace roots whose package graph contains the
+    /// given package.
+    ///
+    /// Since the graph of a root contains all of its transitive dependencies,
+    /// these are the roots of the package itself and of all packages depending
+    /// on it. The driver only checks entire roots, so this is the smallest set
+    /// of roots which rechecks the package along with its reverse dependencies.
+    fn roots_depending_on(&self, package: PackageId) -> HashSet<PathBuf> {
+        self.checked
+            .root_packages
+            .iter()
+            .filter(|(_, packages)| packages.contains(&package))
+            .map(|(root_path, _)| root_path.clone())
+            .collect()
+    }
+
+    /// Checks the roots of the current workspace with the given paths, or all
+    /// roots if [`None`], and sends any raised diagnostics to the client.
+    ///
+    /// Packages and diagnostics of roots which aren't checked are retained from
+    /// the previous check.
+    fn compile_roots(&mut self, affected: Option<&HashSet<PathBuf>>) {
+        let progress = self.begin_progress("Checking workspace");
+        let started_at = std::time::Instant::now();
+
+        let published = std::mem::take(self.error_files_curr.get_mut().unwrap());
+        *self.error_files_prev.get_mut().unwrap() = published.keys().cloned().collect();
+
+        // Diagnostics outside of the checked roots won't be raised again, so they are
+        // kept as published, rather than being cleared when draining.
+        if let Some(affected) = affected {
+            let retained = published.into_iter().filter(|(uri, _)| {
+                let path = path_from_uri(uri);
+
+                !affected.iter().any(|root_path| path.starts_with(root_path))
+            });
+
+            self.error_files_curr.get_mut().unwrap().extend(retained);
+        }
 
-        let path = PathBuf::from(self.vfs.workspace_root.as_str());
-        let handle = self.dcx.handle();
+        let mut graph: Option<CheckedPackageGraph> = None;
+        let mut package_roots = IndexMap::new();
+        let mut root_packages = IndexMap::new();
+        let mut has_failed = false;
+
+        for root in &self.roots {
+            let root_path = root.path();
+
+            if affected.is_some_and(|affected| !affected.contains(&root_path)) {
+                continue;
+            }
+
+            match self.check_root(root) {
+                Ok(root_graph) => {
+                    package_roots.extend(root_graph.packages.keys().map(|id| (*id, root_path.clone())));
+                    root_packages.insert(root_path, root_graph.packages.keys().copied().collect());
+
+                    // Packages which are shared between roots, such as the standard library,
+                    // are checked once per root, but only a single copy is kept.
+                    match &mut graph {
+                        Some(graph) => graph.packages.extend(root_graph.packages),
+                        None => graph = Some(root_graph),
+                    }
+                }
+                Err(err) => {
+                    self.dcx.emit(err);
+                    has_failed = true;
+                }
+            }
+        }
+
+        if has_failed {
+            // Keep the graph from the last successful check, so symbol queries keep
+            // working while the workspace is broken, even if they may be outdated.
+            if self.checked.is_stale {
+                log::warn!("check failed again, still serving symbols from last successful check");
+            } else if self.checked.graph.packages.is_empty() {
+                log::warn!("check failed before any successful check, no symbols are available");
+            } else {
+                log::warn!("check failed, serving stale symbols from last successful check");
+            }
+
+            self.checked.is_stale = true;
+        } else {
+            let mut packages = graph.unwrap_or_default();
+
+            if affected.is_some() {
+                let mut previous = std::mem::take(&mut self.checked.graph);
+                previous.packages.extend(packages.packages);
+                packages = previous;
+
+                let mut previous_roots = std::mem::take(&mut self.checked.package_roots);
+                previous_roots.extend(package_roots);
+                package_roots = previous_roots;
+
+                let mut previous_packages = std::mem::take(&mut self.checked.root_packages);
+                previous_packages.extend(root_packages);
+                root_packages = previous_packages;
+            }
+
+            self.report_progress(
+                progress.as_ref(),
+                &format!("Indexing {} packages", packages.packages.len()),
+            );
+            self.checked.update_symbol_lookup(packages, package_roots);
+            self.checked.root_packages = root_packages;
+            self.remap_documents();
+        }
+
+        // Diagnostics must be drained even if the check succeeded, so warnings are
+        // reported and files which no longer have any diagnostics are cleared.
+        self.drain_dcx_diagnostics();
+
+        log::info!(
+            "checked {} of {} roots in {:?}",
+            affected.map_or(self.roots.len(), HashSet::len),
+            self.roots.len(),
+            started_at.elapsed()
+        );
+
+        let message = if self.checked.is_stale { "Check failed" } else { "Done" };
+        self.end_progress(progress, message);
+    }
+
+    /// Maps all open documents onto the source file of the package which
+    /// contains them, so documents which were opened before their package was
+    /// discovered resolve to the symbols of the package.
+    fn remap_documents(&mut self) {
+        let remapped = self
+            .vfs
+            .source_files
+            .values()
+            .filter_map(|document| {
+                let source = self.source_of_uri(&document.uri)?;
+
+                (source.id != document.file.id || source.package != document.file.package)
+                    .then(|| (document.uri.clone(), source))
+            })
+            .collect::<Vec<_>>();
+
+        for (uri, source) in remapped {
+            let Some(document) = self.vfs.get_document(&uri) else {
+                continue;
+            };
+
+            let file = Arc::new(SourceFile {
+                id: source.id,
+                name: source.name.clone(),
+                content: document.file.content.clone(),
+                package: source.package,
+            });
+
+            self.vfs.add_document(uri, file);
+        }
+    }
+
+    /// Checks the package graph within the given root of the workspace.
+    fn check_root(&self, root: &WorkspaceRoot) -> lume_errors::Result<CheckedPackageGraph> {
+        log::debug!("compiling workspace at {}", root.uri.as_str());
+
+        let path = root.path();
+
+        let driver = match lume_driver::Driver::from_root(&path, self.dcx.handle()) {
+            Ok(driver) => driver,
+            Err(err) => {
+                // Failing to load the package is usually caused by a missing or malformed
+                // `Arcfile`, which isn't reported within any open document.
+                self.show_message(
+                    MessageType::ERROR,
+                    &format!("could not load package at {}: {}", path.display(), err.message()),
+                );
+
+                self.report_arcfile_error(&path, &err.message());
+
+                return Err(err);
+            }
+        };
 
-        let check = || -> lume_errors::Result<CheckedPackageGraph> {
-            let driver = lume_driver::Driver::from_root(&path, handle)?;
-            let source_overrides = self.vfs.build_source_overrides();
+        // Loading the package graph may already raise diagnostics, such as within an
+        // `Arcfile`, which can be shown while the rest of the graph is being checked.
+        self.flush_dcx_diagnostics();
 
+        let source_overrides = self.vfs.build_source_overrides(root);
+
+        // Compiler bugs on malformed input shouldn't take down the entire session, so
+        // panics are treated as a failed check, keeping the last successful graph.
+        let result = crate::catch_panic(|| {
             driver.check(lume_session::Options {
                 source_overrides: Some(source_overrides),
                 ..Default::default()
             })
-        };
+        });
 
-        match check() {
-            Ok(packages) => {
-                self.checked.update_symbol_lookup(packages);
-            }
-            Err(err) => {
-                self.dcx.emit(err);
-                self.drain_dcx_diagnostics();
+        match result {
+            Ok(result) => result,
+            Err(payload) => {
+                log::error!("check of {} panicked: {payload}", path.display());
+
+                self.show_message(
+                    MessageType::WARNING,
+                    &format!(
+                        "Checking {} crashed, so results from the last successful check are shown: {payload}",
+                        path.display()
+                    ),
+                );
+
+                Err(SimpleDiagnostic::new(format!("check of {} panicked: {payload}", path.display())).into())
             }
         }
     }
 
+    /// Reports the given error within the `Arcfile` within the given directory,
+    /// so a package which fails to load is marked within the file which has to
+    /// be fixed, rather than only in a transient message.
+    ///
+    /// The error is reported at the key which is named by the error, if any.
+    /// Otherwise, it's reported on the first line of the `Arcfile`.
+    fn report_arcfile_error(&self, directory: &Path, message: &str) {
+        let arcfile_path = directory.join("Arcfile");
+        let Ok(content) = std::fs::read_to_string(&arcfile_path) else {
+            return;
+        };
+
+        let Some(uri) = uri_from_path(&arcfile_path) else {
+            return;
+        };
+
+        let index =
+            arcfile_key_range(&content, message).unwrap_or_else(|| 0..content.lines().next().map_or(0, str::len));
+
+        let diagnostic = Diagnostic {
+            range: crate::position::position_from_range(&content, &index, self.position_encoding),
+            severity: Some(lsp_types::DiagnosticSeverity::ERROR),
+            source: Some(String::from(crate::diagnostics::LSP_SOURCE_LUME)),
+            message: format!("could not load package: {message}"),
+            ..Default::default()
+        };
+
+        let mut curr = self.error_files_curr.write().unwrap();
+
+        let published = curr.entry(uri.clone()).or_default();
+        published.push(diagnostic);
+
+        self.publish_diagnostics_to_file(published, uri);
+    }
+
+    /// Finds the `Arcfile` of the package which contains the given document,
+    /// or of the first root of the workspace if no document is given.
+    pub(crate) fn arcfile_of(&self, uri: Option<&Uri>) -> Option<Uri> {
+        let directory = match uri {
+            Some(uri) => path_from_uri(uri),
+            None => self.roots.first()?.path(),
+        };
+
+        let package_root = directory
+            .ancestors()
+            .find(|directory| directory.join("Arcfile").exists())?;
+
+        uri_from_path(&package_root.join("Arcfile"))
+    }
+
+    /// Parses the open document with the given URI on its own, reporting any
+    /// syntax errors to the client right away.
+    ///
+    /// Returns `true` if the document parsed without errors, meaning the
+    /// workspace should be checked in full. Documents which aren't open are
+    /// assumed to be valid.
+    pub(crate) fn check_syntax(&self, uri: &Uri) -> bool {
+        let Some(document) = self.vfs.get_document(uri) else {
+            return true;
+        };
+
+        let mut parser = lume_parser::Parser::new(document.file.clone(), self.dcx.handle());
+
+        if let Err(err) = parser.parse() {
+            self.dcx.emit(err);
+        }
+
+        let mut has_errors = false;
+
+        self.dcx.with_iter(|diagnostics| {
+            has_errors = diagnostics
+                .into_iter()
+                .any(|diagnostic| diagnostic.severity() == error_snippet::Severity::Error);
+        });
+
+        if !has_errors {
+            self.dcx.clear();
+            return true;
+        }
+
+        log::debug!("document {} has syntax errors, skipping full check", uri.as_str());
+
+        // The syntax errors replace all diagnostics of the document from the last
+        // check, instead of being published alongside them.
+        self.error_files_curr.write().unwrap().shift_remove(uri);
+        self.drain_dcx_diagnostics();
+
+        false
+    }
+
+    /// Changes the roots of the workspace, reloading the exclusion filter
+    /// relative to each of the new roots.
+    pub(crate) fn set_workspace_roots(&mut self, roots: Vec<Uri>) {
+        self.roots = roots
+            .into_iter()
+            .map(|root| WorkspaceRoot::new(root, &self.config))
+            .collect();
+    }
+
+    /// Gets the URIs of all roots of the workspace.
+    pub(crate) fn workspace_root_uris(&self) -> Vec<Uri> {
+        self.roots.iter().map(|root| root.uri.clone()).collect()
+    }
+
+    /// Finds the root of the workspace which contains the given path. If roots
+    /// are nested within each other, the innermost root is returned.
+    pub(crate) fn root_of_path(&self, path: &Path) -> Option<&WorkspaceRoot> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(root.path()))
+            .max_by_key(|root| root.path().components().count())
+    }
+
+    /// Determines whether the document with the given URI is within any of the
+    /// workspace roots, as opposed to files of dependencies outside of them.
+    pub(crate) fn is_workspace_file(&self, uri: &Uri) -> bool {
+        self.root_of_path(&path_from_uri(uri)).is_some()
+    }
+
+    /// Determines whether the document with the given URI should be managed by
+    /// the server, based on its extension and the exclusion filter.
+    pub(crate) fn is_tracked(&self, uri: &Uri) -> bool {
+        self.config.is_tracked(uri) && !self.is_excluded(uri)
+    }
+
+    /// Determines whether the document with the given URI is excluded from
+    /// checking, by matching its path relative to the root which contains it.
+    pub(crate) fn is_excluded(&self, uri: &Uri) -> bool {
+        let file_path = path_from_uri(uri);
+
+        let Some(root) = self.root_of_path(&file_path) else {
+            return false;
+        };
+
+        match file_path.strip_prefix(root.path()) {
+            Ok(relative_path) => root.exclude.is_excluded(relative_path),
+            Err(_) => false,
+        }
+    }
+
     pub(crate) fn source_of_uri(&self, uri: &Uri) -> Option<Arc<SourceFile>> {
-        let file_path = PathBuf::from(uri.path().as_str());
+        let file_path = path_from_uri(uri);
+
+        // Source files are named relative to the root they were checked from, so
+        // files with the same name in different roots can only be told apart by
+        // joining them with the root of their package.
+        for (id, package) in &self.checked.graph.packages {
+            let Some(root_path) = self.checked.package_roots.get(id) else {
+                continue;
+            };
+
+            for source in package.sources.iter() {
+                if root_path.join(source.name.to_pathbuf()) == file_path {
+                    return Some(source.clone());
+                }
+            }
+        }
 
         for package in self.checked.graph.packages.values() {
             for source in package.sources.iter() {
@@ -84,18 +536,35 @@ impl State {
         None
     }
 
+    /// Gets the package with the given ID from the latest checked graph.
+    ///
+    /// Symbols may refer to packages which are no longer part of the graph,
+    /// such as after the graph was replaced, in which case a warning is logged
+    /// and [`None`] is returned.
+    pub(crate) fn package_of(&self, id: PackageId) -> Option<&lume_driver::CheckedPackage> {
+        let package = self.checked.graph.packages.get(&id);
+
+        if package.is_none() {
+            log::warn!("package {id:?} is missing from the package graph");
+        }
+
+        package
+    }
+
     pub(crate) fn location_of(&self, uri: &Uri, line: usize, column: usize) -> Option<Location> {
         let source_file = self.vfs.get_document(uri)?;
 
-        let mut index = column;
-        for (line_idx, line_str) in source_file.file.content.lines().enumerate() {
-            if line_idx >= line {
-                break;
-            }
+        // Columns are given in the negotiated encoding, which need to be converted
+        // into byte offsets before they can be used to index into the source file.
+        let index = index_from_position(&source_file.file.content, line, column, self.position_encoding);
 
-            // +1 for the newline.
-            index += line_str.len() + 1;
-        }
+        self.location_at(uri, index)
+    }
+
+    /// Creates a single-character [`Location`] at the given byte index within
+    /// the given document.
+    pub(crate) fn location_at(&self, uri: &Uri, index: usize) -> Option<Location> {
+        let source_file = self.vfs.get_document(uri)?;
 
         let range = index..index + 1;
 
@@ -108,7 +577,96 @@ impl State {
         )
     }
 
+    /// Converts the given [`Location`] into an LSP-compatible location, which
+    /// can be sent to the client.
+    ///
+    /// If the file of the location cannot be represented as a URI, [`None`] is
+    /// returned.
+    pub(crate) fn lsp_location_of(&self, location: Location) -> Option<lsp_types::Location> {
+        let file_path = location.file.name.to_pathbuf();
+
+        // Relative paths are resolved against the root which the package was
+        // checked from, since the same path may exist within multiple roots.
+        let uri = match self.checked.package_roots.get(&location.file.package) {
+            Some(root_path) if file_path.is_relative() => self.uri_of_path(&root_path.join(file_path))?,
+            _ => self.uri_of_path(&file_path)?,
+        };
+
+        let range = self.lsp_range_of(location);
+
+        Some(lsp_types::Location { uri, range })
+    }
+
+    /// Converts the given [`Location`] into an LSP-compatible range, using the
+    /// negotiated position encoding.
+    pub(crate) fn lsp_range_of(&self, location: Location) -> lsp_types::Range {
+        lsp_range_of(location, self.position_encoding)
+    }
+
+    /// Converts the given file path into an absolute URI. Relative paths are
+    /// resolved against the first root of the workspace which contains the
+    /// path as an open document or on disk.
+    ///
+    /// If the path refers to an open document, the URI of the document is
+    /// returned, so it exactly matches the URI which the client sent. If the
+    /// path cannot be represented as a URI, [`None`] is returned.
+    pub(crate) fn uri_of_path(&self, file_path: &Path) -> Option<Uri> {
+        let absolute = if file_path.is_absolute() {
+            file_path.to_path_buf()
+        } else {
+            let candidates = self.roots.iter().map(|root| root.path().join(file_path));
+
+            candidates
+                .clone()
+                .find(|path| self.vfs.document_at_path(path).is_some() || path.exists())
+                .or_else(|| candidates.clone().next())
+                .unwrap_or_else(|| file_path.to_path_buf())
+        };
+
+        if let Some(document) = self.vfs.document_at_path(&absolute) {
+            return Some(document.uri.clone());
+        }
+
+        let uri = uri_from_path(&absolute);
+
+        if uri.is_none() {
+            log::warn!("could not create URI from {}", absolute.display());
+        }
+
+        uri
+    }
+
+    /// Shows the given message to the user, for errors which aren't tied to any
+    /// specific document.
+    pub(crate) fn show_message(&self, typ: MessageType, message: &str) {
+        let params = ShowMessageParams {
+            typ,
+            message: message.to_owned(),
+        };
+
+        let notification = lsp_server::Notification::new(ShowMessage::METHOD.to_owned(), params);
+
+        if let Err(err) = self.dispatcher.send(Message::Notification(notification)) {
+            log::error!("could not show message: {err}");
+        }
+    }
+
+    /// Creates a new, unique ID for a request sent to the client, starting with
+    /// the given prefix.
+    ///
+    /// Requests may still be awaiting a response when the next one is sent, so
+    /// IDs must never be reused.
+    pub(crate) fn next_request_id(&self, prefix: &str) -> RequestId {
+        let counter = self.request_counter.fetch_add(1, Ordering::Relaxed) + 1;
+
+        RequestId::from(format!("{prefix}/{counter}"))
+    }
+
     pub(crate) fn ok<T: serde::Serialize>(&self, id: RequestId, message: &T) -> Result<()> {
+        if self.is_cancelled(&id) {
+            return self.err(id, ErrorCode::RequestCanceled, "request was cancelled");
+        }
+
         let value = match serde_json::to_value(message) {
             Ok(val) => val,
             Err(err) => return Err(err.into_diagnostic()),
@@ -123,7 +681,17 @@ impl State {
     }
 
     pub(crate) fn err(&self, id: RequestId, code: ErrorCode, message: &str) -> Result<()> {
-        let resp = Response::new_err(id, code as i32, message.into());
+        // Cancelled requests must always be responded to with the same error, no
+        // matter whether the request succeeded or failed.
+        let resp = if self.is_cancelled(&id) {
+            Response::new_err(
+                id,
+                ErrorCode::RequestCanceled as i32,
+                String::from("request was cancelled"),
+            )
+        } else {
+            Response::new_err(id, code as i32, message.into())
+        };
 
         match self.dispatcher.send(Message::Response(resp)) {
             Ok(()) => Ok(()),
@@ -145,28 +713,43 @@ impl From<&Uri> for SourceFileId {
     }
 }
 
+#[derive(Default)]
 pub(crate) struct Vfs {
-    pub(crate) workspace_root: Uri,
-
     source_files: IndexMap<SourceFileId, MappedSourceFile>,
 }
 
 impl Vfs {
-    pub fn new(root: Uri) -> Self {
-        Self {
-            workspace_root: root,
-            source_files: IndexMap::new(),
-        }
-    }
-
     pub fn get_document(&self, uri: &Uri) -> Option<&MappedSourceFile> {
         self.source_files.values().find(|file| &file.uri == uri)
     }
 
+    /// Finds the open document which is located at the given absolute path.
+    ///
+    /// If no document has the exact same path, the canonical paths are
+    /// compared instead, so paths which differ by symbolic links or casing
+    /// still resolve to the same document.
+    pub fn document_at_path(&self, path: &Path) -> Option<&MappedSourceFile> {
+        if let Some(document) = self.source_files.values().find(|file| path_from_uri(&file.uri) == path) {
+            return Some(document);
+        }
+
+        let canonical_path = std::fs::canonicalize(path).ok()?;
+
+        self.source_files
+            .values()
+            .find(|file| file.canonical_path.as_ref() == Some(&canonical_path))
+    }
+
     pub fn add_document(&mut self, uri: Uri, file: Arc<SourceFile>) {
         let id: SourceFileId = (&uri).into();
 
-        self.source_files.insert(id, MappedSourceFile { uri, file });
+        let canonical_path = std::fs::canonicalize(path_from_uri(&uri)).ok();
+
+        self.source_files.insert(id, MappedSourceFile {
+            uri,
+            file,
+            canonical_path,
+        });
     }
 
     pub fn remove_document(&mut self, uri: &Uri) -> bool {
@@ -191,21 +774,67 @@ impl Vfs {
         );
     }
 
+    /// Applies the given list of content changes to the document, in order.
+    ///
+    /// Changes with a range replace only the text within that range, whereas
+    /// changes without a range replace the entire document.
+    pub fn apply_changes(
+        &mut self,
+        uri: &Uri,
+        changes: Vec<TextDocumentContentChangeEvent>,
+        encoding: PositionEncoding,
+    ) {
+        let Some(document) = self.get_document(uri) else {
+            return;
+        };
+
+        let mut content = document.file.content.clone();
+
+        for change in changes {
+            let Some(range) = change.range else {
+                content = change.text;
+                continue;
+            };
+
+            let start = index_from_position(
+                &content,
+                range.start.line as usize,
+                range.start.character as usize,
+                encoding,
+            );
+
+            let end = index_from_position(
+                &content,
+                range.end.line as usize,
+                range.end.character as usize,
+                encoding,
+            );
+
+            content.replace_range(start..end.max(start), &change.text);
+        }
+
+        self.change_document(uri, content);
+    }
+
     /// Builds the overrides of source files which we currently have in-memory
     /// in the language server.
     ///
     /// Some of these might not need to be overwritten, as they are the same as
     /// they are on the disk. But, since the operation is a
     /// [`IndexMap::extend`]-call, it's a relatively quick operation.
-    fn build_source_overrides(&self) -> IndexMap<FileName, String> {
+    fn build_source_overrides(&self, root: &WorkspaceRoot) -> IndexMap<FileName, String> {
         let mut source_overrides = IndexMap::new();
+        let workspace_root = root.path();
 
         for source_file in self.source_files.values() {
-            let file_path = PathBuf::from(source_file.uri.path().as_str());
-            let workspace_root = self.workspace_root.path().as_str();
+            let file_path = path_from_uri(&source_file.uri);
 
-            let relative_path = if file_path.starts_with(workspace_root) {
-                FileName::Real(file_path.strip_prefix(workspace_root).unwrap().to_path_buf())
+            let relative_path = if let Ok(relative_path) = file_path.strip_prefix(&workspace_root) {
+                if root.exclude.is_excluded(relative_path) {
+                    continue;
+                }
+
+                FileName::Real(relative_path.to_path_buf())
             } else {
                 FileName::Real(file_path)
             };
@@ -221,34 +850,369 @@ impl Vfs {
 pub(crate) struct MappedSourceFile {
     pub(crate) uri: Uri,
     pub(crate) file: Arc<SourceFile>,
+
+    /// Canonical path of the document on disk, if it exists, which resolves
+    /// symbolic links and differences in casing.
+    pub(crate) canonical_path: Option<PathBuf>,
 }
 
 #[derive(Default)]
 pub(crate) struct CheckedWorkspace {
     pub graph: CheckedPackageGraph,
-    pub symbols: SymbolLookup,
+    pub symbols: WorkspaceSymbols,
+
+    /// Path of the workspace root which each package was checked from.
+    pub package_roots: IndexMap<PackageId, PathBuf>,
+
+    /// Packages within the graph of each workspace root, including the
+    /// dependencies of the root, keyed by the path of the root.
+    pub root_packages: IndexMap<PathBuf, HashSet<PackageId>>,
+
+    /// Whether the latest check of the workspace failed, meaning the graph is
+    /// retained from an earlier, successful check.
+    pub is_stale: bool,
+
+    /// Version of the graph, which is incremented each time it is replaced.
+    pub version: u64,
+
+    /// Hover content of recently hovered positions within the current graph.
+    pub hover_cache: HoverCache,
 }
 
 impl CheckedWorkspace {
-    pub fn update_symbol_lookup(&mut self, graph: CheckedPackageGraph) {
-        let mut symbols = SymbolLookup::default();
-        for package in graph.packages.values() {
-            let package_symbols = match SymbolLookup::from_hir(package.tcx.hir()) {
-                Ok(syms) => syms,
-                Err(err) => {
-                    log::error!(
-                        "error while updating symbol graph for package {}: {}",
-                        package.package,
-                        err.message()
-                    );
-                    continue;
-                }
-            };
+    /// Discards all cached symbol lookups, so they are rebuilt from scratch on
+    /// the next update.
+    pub fn clear_symbol_cache(&mut self) {
+        self.symbols = WorkspaceSymbols::default();
+    }
 
-            symbols.extend(package_symbols);
+    pub fn update_symbol_lookup(&mut self, graph: CheckedPackageGraph, package_roots: IndexMap<PackageId, PathBuf>) {
+        self.symbols.update(&graph);
+        self.graph = graph;
+        self.package_roots = package_roots;
+        self.is_stale = false;
+
+        self.version += 1;
+        self.hover_cache.clear();
+    }
+}
+
+/// Finds the key within the given `Arcfile` content which is named by the
+/// given error message, such as ``missing value for `version` ``.
+///
+/// Names are taken from backticks or quotes within the message, and only match
+/// where they're declared as a key, being followed by a `:` or `=`.
+fn arcfile_key_range(content: &str, message: &str) -> Option<std::ops::Range<usize>> {
+    let names = message
+        .split(['`', '\'', '"'])
+        .skip(1)
+        .step_by(2)
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-'));
+
+    for name in names {
+        let mut line_start = 0;
+
+        for line in content.split_inclusive('\n') {
+            let indent = line.len() - line.trim_start().len();
+
+            if let Some(rest) = line[indent..].strip_prefix(name)
+                && (rest.trim_start().starts_with(':') || rest.trim_start().starts_with('='))
+            {
+                let start = line_start + indent;
+
+                return Some(start..start + name.len());
+            }
+
+            line_start += line.len();
         }
+    }
 
-        self.graph = graph;
-        self.symbols = symbols;
+    None
+}
+
+/// Folder which is opened as a root of the workspace, containing one-or-more
+/// packages.
+pub(crate) struct WorkspaceRoot {
+    pub uri: Uri,
+
+    /// Filter of paths within the root which are excluded from checking.
+    pub exclude: ExcludeFilter,
+}
+
+impl WorkspaceRoot {
+    pub fn new(uri: Uri, config: &Config) -> Self {
+        let exclude = ExcludeFilter::new(&path_from_uri(&uri), &config.exclude, config.respect_gitignore);
+
+        Self { uri, exclude }
+    }
+
+    /// Gets the path of the root on disk.
+    pub fn path(&self) -> PathBuf {
+        path_from_uri(&self.uri)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::str::FromStr;
+
+    use crossbeam::channel::Receiver;
+    use lsp_types::{Position, Range};
+
+    use super::*;
+
+    /// Creates a new state without any workspace roots, along with the receiver
+    /// of all messages which the state sends to the client.
+    pub(crate) fn test_state() -> (State, Receiver<Message>) {
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let state = State::new(sender, Vec::new(), PositionEncoding::Utf16, Config::default());
+
+        (state, receiver)
+    }
+
+    /// Opens a document with the given URI and content, which doesn't belong to
+    /// any package.
+    pub(crate) fn open_test_document(state: &mut State, uri: &str, content: &str) -> Uri {
+        let uri = Uri::from_str(uri).unwrap();
+        let file = SourceFile::new(
+            PackageId::empty(),
+            FileName::Real(path_from_uri(&uri)),
+            content.to_owned(),
+        );
+
+        state.vfs.add_document(uri.clone(), Arc::new(file));
+
+        uri
+    }
+
+    /// Workspace which is written into a temporary directory, which is removed
+    /// again once dropped.
+    pub(crate) struct TestWorkspace {
+        pub root: PathBuf,
+    }
+
+    impl TestWorkspace {
+        /// Writes the given files, keyed by their path relative to the root of
+        /// the workspace.
+        pub(crate) fn new(files: &[(&str, &str)]) -> Self {
+            static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+            let root = std::env::temp_dir().join(format!(
+                "lume-lsp-test-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+
+            for (name, content) in files {
+                let path = root.join(name);
+
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, content).unwrap();
+            }
+
+            Self { root }
+        }
+
+        /// Gets the URI of the file with the given path, relative to the root
+        /// of the workspace.
+        pub(crate) fn uri_of(&self, name: &str) -> Uri {
+            uri_from_path(&self.root.join(name)).unwrap()
+        }
+    }
+
+    impl Drop for TestWorkspace {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    /// Renders the `Arcfile` of a package with the given name, along with the
+    /// given dependency entries.
+    pub(crate) fn test_arcfile(name: &str, dependencies: &str) -> String {
+        format!("Package {{\n    name: \"{name}\"\n    version: \"0.1.0\"\n    dependencies: {{{dependencies}}}\n}}\n")
+    }
+
+    /// Writes the given files into a new workspace, opens all Lume documents
+    /// within it and checks the packages in the given directories, relative to
+    /// the root of the workspace.
+    pub(crate) fn checked_test_state(
+        packages: &[&str],
+        files: &[(&str, &str)],
+    ) -> (State, Receiver<Message>, TestWorkspace) {
+        let workspace = TestWorkspace::new(files);
+        let (mut state, receiver) = test_state();
+
+        let roots = packages
+            .iter()
+            .map(|package| uri_from_path(&workspace.root.join(package).join("")).unwrap())
+            .collect();
+
+        state.set_workspace_roots(roots);
+
+        for (name, content) in files.iter().filter(|(name, _)| name.ends_with(".lm")) {
+            let uri = workspace.uri_of(name);
+
+            open_test_document(&mut state, uri.as_str(), content);
+        }
+
+        state.compile_workspace();
+        assert!(!state.checked.is_stale, "workspace fixture failed to check");
+
+        (state, receiver, workspace)
+    }
+
+    /// Replaces the entire content of the open document with the given URI, as
+    /// sent by clients with full document synchronization.
+    pub(crate) fn replace_test_document(state: &mut State, uri: &Uri, content: &str) {
+        let change = TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: content.to_owned(),
+        };
+
+        state.vfs.apply_changes(uri, vec![change], PositionEncoding::Utf16);
+    }
+
+    /// Creates a location at the first occurrence of `needle` within the open
+    /// document with the given URI.
+    pub(crate) fn location_of_text(state: &State, uri: &Uri, needle: &str) -> Location {
+        let content = &state.vfs.get_document(uri).unwrap().file.content;
+        let index = content.find(needle).unwrap();
+
+        state.location_at(uri, index).unwrap()
+    }
+
+    fn content_of(state: &State, uri: &Uri) -> String {
+        state.vfs.get_document(uri).unwrap().file.content.clone()
+    }
+
+    fn change(range: Option<Range>, text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range,
+            range_length: None,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn empty_change_list_keeps_content() {
+        let (mut state, _) = test_state();
+        let uri = open_test_document(&mut state, "file:///project/main.lm", "let a = 1;");
+
+        state.vfs.apply_changes(&uri, Vec::new(), PositionEncoding::Utf16);
+
+        assert_eq!(content_of(&state, &uri), "let a = 1;");
+    }
+
+    #[test]
+    fn ranged_changes_are_applied_in_order() {
+        let (mut state, _) = test_state();
+        let uri = open_test_document(&mut state, "file:///project/main.lm", "let a = 1;\r\nlet b = 2;");
+
+        let changes = vec![
+            change(Some(Range::new(Position::new(1, 4), Position::new(1, 5))), "c"),
+            change(Some(Range::new(Position::new(0, 8), Position::new(0, 9))), "😀"),
+            change(Some(Range::new(Position::new(0, 11), Position::new(0, 11))), " // x"),
+        ];
+
+        state.vfs.apply_changes(&uri, changes, PositionEncoding::Utf16);
+
+        assert_eq!(content_of(&state, &uri), "let a = 😀; // x\r\nlet c = 2;");
+    }
+
+    #[test]
+    fn full_changes_replace_content() {
+        let (mut state, _) = test_state();
+        let uri = open_test_document(&mut state, "file:///project/main.lm", "let a = 1;");
+
+        state
+            .vfs
+            .apply_changes(&uri, vec![change(None, "let b = 2;")], PositionEncoding::Utf16);
+
+        assert_eq!(content_of(&state, &uri), "let b = 2;");
+    }
+
+    #[test]
+    fn failed_check_keeps_previous_graph() {
+        let (mut state, _messages, workspace) = checked_test_state(&[""], &[
+            ("Arcfile", &test_arcfile("app", "")),
+            (
+                "src/main.lm",
+                "fn add(a: Int32, b: Int32) -> Int32 {\n    return a + b;\n}\n",
+            ),
+        ]);
+
+        let uri = workspace.uri_of("src/main.lm");
+        let version = state.checked.version;
+        let symbol = state
+            .checked
+            .symbols
+            .lookup_position(location_of_text(&state, &uri, "add"))
+            .cloned();
+        assert!(symbol.is_some());
+
+        // Without an `Arcfile`, the package can no longer be loaded.
+        std::fs::remove_file(workspace.root.join("Arcfile")).unwrap();
+
+        for _ in 0..2 {
+            state.compile_workspace();
+
+            assert!(state.checked.is_stale);
+            assert_eq!(state.checked.version, version);
+            assert_eq!(
+                state
+                    .checked
+                    .symbols
+                    .lookup_position(location_of_text(&state, &uri, "add"))
+                    .cloned(),
+                symbol
+            );
+        }
+    }
+
+    #[test]
+    fn edits_recheck_roots_depending_on_the_package() {
+        let (state, _messages, workspace) = checked_test_state(&["a", "b", "c"], &[
+            ("a/Arcfile", &test_arcfile("a", "")),
+            ("a/src/lib.lm", "pub fn one() -> Int32 {\n    return 1;\n}\n"),
+            ("b/Arcfile", &test_arcfile("b", "\"a\": { path: \"../a\" }")),
+            ("b/src/main.lm", "import a (one)\n\nfn main() {\n    one();\n}\n"),
+            ("c/Arcfile", &test_arcfile("c", "")),
+            ("c/src/main.lm", "fn main() {}\n"),
+        ]);
+
+        let package_of = |name: &str| state.source_of_uri(&workspace.uri_of(name)).unwrap().package;
+
+        assert_eq!(
+            state.roots_depending_on(package_of("a/src/lib.lm")),
+            HashSet::from([workspace.root.join("a"), workspace.root.join("b")])
+        );
+        assert_eq!(
+            state.roots_depending_on(package_of("b/src/main.lm")),
+            HashSet::from([workspace.root.join("b")])
+        );
+        assert_eq!(
+            state.roots_depending_on(package_of("c/src/main.lm")),
+            HashSet::from([workspace.root.join("c")])
+        );
+    }
+
+    const ARCFILE: &str = "Package {\n    name: \"app\"\n    version = \"0.1\"\n}\n";
+
+    #[test]
+    fn arcfile_errors_point_at_named_key() {
+        assert_eq!(
+            arcfile_key_range(ARCFILE, "invalid version `0.1`: expected `version` to be semver"),
+            Some(30..37)
+        );
+        assert_eq!(arcfile_key_range(ARCFILE, "unknown package 'name'"), Some(14..18));
+    }
+
+    #[test]
+    fn arcfile_errors_ignore_values_and_missing_keys() {
+        // Names which only appear as values aren't keys.
+        assert_eq!(arcfile_key_range(ARCFILE, "package `app` not found"), None);
+        assert_eq!(arcfile_key_range(ARCFILE, "missing field `dependencies`"), None);
+        assert_eq!(arcfile_key_range(ARCFILE, "could not read Arcfile"), None);
     }
 }
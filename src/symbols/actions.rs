@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, NumberOrString, Range, TextEdit, Uri, WorkspaceEdit,
+};
+use lume_errors::Result;
+use lume_span::{Location, SourceFile};
+
+use crate::diagnostics::{MISSING_TYPE_CODES, UNUSED_VARIABLE_CODE};
+use crate::position::{index_from_position, position_from_index};
+use crate::state::State;
+use crate::symbols::folding::import_lines;
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Gets all quick fixes which can be applied to resolve the given diagnostics
+/// within the given file.
+///
+/// Diagnostics are identified by their code, but each fix is only offered if
+/// the diagnostic can also be mapped back to a matching HIR node:
+/// - unused variables can be prefixed with `_`;
+/// - unknown types can be replaced with the fully-qualified name of a type with
+///   the same name, elsewhere in the workspace, or the type can be imported.
+pub(crate) fn code_actions_for(
+    state: &State,
+    uri: &Uri,
+    file: &SourceFile,
+    diagnostics: &[Diagnostic],
+) -> Result<Vec<CodeActionOrCommand>> {
+    let Some(package) = state.checked.graph.packages.get(&file.package) else {
+        return Ok(Vec::new());
+    };
+
+    let mut visitor = VariableVisitor {
+        file,
+        names: Vec::new(),
+    };
+
+    traverse(package.tcx.hir(), &mut visitor)?;
+
+    let mut actions = Vec::new();
+
+    for diagnostic in diagnostics {
+        let Some(NumberOrString::String(code)) = &diagnostic.code else {
+            continue;
+        };
+
+        let start = index_of(state, file, diagnostic.range.start);
+        let end = index_of(state, file, diagnostic.range.end);
+
+        if code == UNUSED_VARIABLE_CODE {
+            let names = visitor
+                .names
+                .iter()
+                .filter(|name| name.index.start < end.max(start + 1) && name.index.end > start);
+
+            for name in names {
+                let text = &file.content[name.index.clone()];
+                if text.starts_with('_') {
+                    continue;
+                }
+
+                let position = position_from_index(&file.content, name.index.start, state.position_encoding);
+
+                actions.push(quick_fix(
+                    format!("Prefix `{text}` with an underscore"),
+                    diagnostic,
+                    uri,
+                    TextEdit {
+                        range: Range::new(position, position),
+                        new_text: String::from("_"),
+                    },
+                ));
+            }
+        } else if MISSING_TYPE_CODES.contains(&code.as_str()) {
+            let text = file.content.get(start..end).unwrap_or_default().trim();
+            if text.is_empty() || text.contains("::") {
+                continue;
+            }
+
+            for qualified_name in types_named(state, text) {
+                if let Some(edit) = import_edit(state, file, &qualified_name) {
+                    actions.push(quick_fix(format!("Import `{qualified_name}`"), diagnostic, uri, edit));
+                }
+
+                actions.push(quick_fix(
+                    format!("Use `{qualified_name}`"),
+                    diagnostic,
+                    uri,
+                    TextEdit {
+                        range: diagnostic.range,
+                        new_text: qualified_name,
+                    },
+                ));
+            }
+        }
+    }
+
+    Ok(actions)
+}
+
+fn index_of(state: &State, file: &SourceFile, position: lsp_types::Position) -> usize {
+    index_from_position(
+        &file.content,
+        position.line as usize,
+        position.character as usize,
+        state.position_encoding,
+    )
+}
+
+/// Gets the fully-qualified names of all types within the workspace with the
+/// given name, which are declared within a namespace.
+fn types_named(state: &State, name: &str) -> Vec<String> {
+    let mut names = Vec::new();
+
+    for package in state.checked.graph.packages.values() {
+        for node in package.tcx.hir().nodes().values() {
+            let lume_hir::Node::Type(type_def) = node else {
+                continue;
+            };
+
+            let type_name = match type_def {
+                lume_hir::TypeDefinition::Struct(struct_def) => &struct_def.name,
+                lume_hir::TypeDefinition::Trait(trait_def) => &trait_def.name,
+                lume_hir::TypeDefinition::Enum(enum_def) => &enum_def.name,
+            };
+
+            let qualified_name = format!("{type_name:+}");
+
+            if type_name.to_string() == name && qualified_name != name && !names.contains(&qualified_name) {
+                names.push(qualified_name);
+            }
+        }
+    }
+
+    names
+}
+
+/// Creates an edit which imports the type with the given fully-qualified name,
+/// inserted after the existing imports of the file, or at the start of the file
+/// if it has no imports.
+fn import_edit(state: &State, file: &SourceFile, qualified_name: &str) -> Option<TextEdit> {
+    let (namespace, name) = qualified_name.rsplit_once("::")?;
+
+    let line = match import_lines(&file.content) {
+        Some((_, end_line)) => end_line + 1,
+        None => 0,
+    };
+
+    let index = file
+        .content
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+
+    // If the last import is on the last line of the file, it has no line break
+    // to insert the new import after.
+    let new_text = if index == file.content.len() && !file.content.ends_with('\n') && line > 0 {
+        format!("\nimport {namespace} ({name})")
+    } else {
+        format!("import {namespace} ({name})\n")
+    };
+
+    let position = position_from_index(&file.content, index, state.position_encoding);
+
+    Some(TextEdit {
+        range: Range::new(position, position),
+        new_text,
+    })
+}
+
+fn quick_fix(title: String, diagnostic: &Diagnostic, uri: &Uri, edit: TextEdit) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// Collects the locations of the names of all variable declarations within a
+/// single file.
+struct VariableVisitor<'a> {
+    file: &'a SourceFile,
+    names: Vec<Location>,
+}
+
+impl Visitor for VariableVisitor<'_> {
+    fn visit_stmt(&mut self, stmt: &lume_hir::Statement) -> Result<()> {
+        if let lume_hir::StatementKind::Variable(decl) = &stmt.kind
+            && decl.name.location.file.id == self.file.id
+        {
+            self.names.push(decl.name.location);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+    use lume_span::{FileName, PackageId};
+
+    use super::*;
+    use crate::position::{PositionEncoding, position_from_range};
+    use crate::state::tests::{checked_test_state, test_arcfile, test_state};
+
+    const VARIABLES: &str = "fn main() {\n    let unused = 1;\n    let _ignored = 2;\n}\n";
+
+    /// Creates a diagnostic with the given code, which covers the first
+    /// occurrence of `needle` within the given content.
+    fn diagnostic_at(content: &str, needle: &str, code: &str) -> Diagnostic {
+        let start = content.find(needle).unwrap();
+
+        Diagnostic {
+            range: position_from_range(content, &(start..start + needle.len()), PositionEncoding::Utf16),
+            code: Some(NumberOrString::String(code.to_owned())),
+            message: format!("{needle} is never used"),
+            ..Default::default()
+        }
+    }
+
+    fn titles_of(diagnostic: Diagnostic) -> Vec<String> {
+        let (state, _messages, workspace) = checked_test_state(&[""], &[
+            ("Arcfile", &test_arcfile("app", "")),
+            ("src/main.lm", VARIABLES),
+        ]);
+
+        let uri = workspace.uri_of("src/main.lm");
+        let file = state.source_of_uri(&uri).unwrap();
+
+        code_actions_for(&state, &uri, &file, &[diagnostic])
+            .unwrap()
+            .into_iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.title,
+                CodeActionOrCommand::Command(command) => command.title,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unused_variables_are_prefixed() {
+        let titles = titles_of(diagnostic_at(VARIABLES, "unused", UNUSED_VARIABLE_CODE));
+
+        assert_eq!(titles, ["Prefix `unused` with an underscore"]);
+    }
+
+    #[test]
+    fn prefixed_variables_are_skipped() {
+        let titles = titles_of(diagnostic_at(VARIABLES, "_ignored", UNUSED_VARIABLE_CODE));
+
+        assert!(titles.is_empty(), "{titles:?}");
+    }
+
+    #[test]
+    fn other_codes_are_skipped() {
+        // The message mentions unused code, but only the code determines the fix.
+        let titles = titles_of(diagnostic_at(VARIABLES, "unused", "unused-import"));
+
+        assert!(titles.is_empty(), "{titles:?}");
+    }
+
+    fn import_edit_of(content: &str) -> TextEdit {
+        let (state, _) = test_state();
+        let file = SourceFile::new(
+            PackageId::empty(),
+            FileName::Real(std::path::PathBuf::from("main.lm")),
+            content.to_owned(),
+        );
+
+        import_edit(&state, &file, "geometry::Point").unwrap()
+    }
+
+    #[test]
+    fn import_is_inserted_at_start_without_imports() {
+        let edit = import_edit_of("fn main() {}\n");
+
+        assert_eq!(edit.range, Range::new(Position::new(0, 0), Position::new(0, 0)));
+        assert_eq!(edit.new_text, "import geometry (Point)\n");
+    }
+
+    #[test]
+    fn import_is_inserted_after_import_block() {
+        let edit = import_edit_of("import std (Int32)\nimport lib (one)\n\nfn main() {}\n");
+
+        assert_eq!(edit.range, Range::new(Position::new(2, 0), Position::new(2, 0)));
+        assert_eq!(edit.new_text, "import geometry (Point)\n");
+    }
+
+    #[test]
+    fn import_is_inserted_after_last_line_without_line_break() {
+        let edit = import_edit_of("import lib (one)");
+
+        assert_eq!(edit.range, Range::new(Position::new(0, 16), Position::new(0, 16)));
+        assert_eq!(edit.new_text, "\nimport geometry (Point)");
+    }
+}
@@ -0,0 +1,212 @@
+use lsp_types::{CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall, SymbolKind as LspSymbolKind};
+use lume_errors::{Result, SimpleDiagnostic};
+use lume_hir::WithLocation as _;
+use lume_infer::query::CallReference;
+use lume_span::{Location, NodeId};
+
+use crate::state::State;
+use crate::symbols::definition::{definition_of, definition_of_symbol};
+use crate::symbols::hover::signature_of_callable;
+use crate::symbols::lookup::{SymbolEntry, SymbolKind};
+
+/// Resolves the callable at the given location into a call hierarchy item.
+///
+/// The location may either refer to the declaration of a callable or to a call
+/// expression which invokes it. Otherwise, returns [`None`].
+pub(crate) fn prepare_call_hierarchy(state: &State, location: Location) -> Result<Option<CallHierarchyItem>> {
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        return Ok(None);
+    };
+
+    let Some(reference) = callable_of_symbol(state, sym)? else {
+        return Ok(None);
+    };
+
+    call_hierarchy_item(state, sym.location, reference).map(Some)
+}
+
+/// Finds all calls to the callable at the given location, across all packages
+/// in the workspace, grouped by the callable which contains the call.
+pub(crate) fn incoming_calls(state: &State, location: Location) -> Result<Vec<CallHierarchyIncomingCall>> {
+    let Some(target) = definition_of(state, location)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut calls: Vec<(Location, CallHierarchyIncomingCall)> = Vec::new();
+
+    for sym in state.checked.symbols.iter() {
+        if !matches!(sym.kind, SymbolKind::Call { .. }) {
+            continue;
+        }
+
+        // Since most calls are not related to the target, errors are expected and can
+        // safely be ignored.
+        match definition_of_symbol(state, sym) {
+            Ok(Some(definition)) if definition == target => {}
+            Ok(_) => continue,
+            Err(err) => {
+                log::debug!("could not resolve call at {}: {}", sym.location, err.message());
+                continue;
+            }
+        }
+
+        let Some((caller_location, caller)) = enclosing_callable(state, sym.location) else {
+            continue;
+        };
+
+        let from_range = state.lsp_range_of(sym.location);
+
+        if let Some((_, existing)) = calls.iter_mut().find(|(caller, _)| *caller == caller_location) {
+            existing.from_ranges.push(from_range);
+            continue;
+        }
+
+        calls.push((caller_location, CallHierarchyIncomingCall {
+            from: call_hierarchy_item(state, caller_location, caller)?,
+            from_ranges: vec![from_range],
+        }));
+    }
+
+    Ok(calls.into_iter().map(|(_, call)| call).collect())
+}
+
+/// Finds all calls made from within the body of the callable at the given
+/// location, grouped by the callable which is invoked.
+pub(crate) fn outgoing_calls(state: &State, location: Location) -> Result<Vec<CallHierarchyOutgoingCall>> {
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        return Ok(Vec::new());
+    };
+
+    let Some(reference) = callable_of_symbol(state, sym)? else {
+        return Ok(Vec::new());
+    };
+
+    let Some(span) = declaration_span(state, sym.location, reference) else {
+        return Ok(Vec::new());
+    };
+
+    let Some(package) = state.package_of(sym.location.file.package) else {
+        return Ok(Vec::new());
+    };
+
+    let mut calls: Vec<(Location, CallHierarchyOutgoingCall)> = Vec::new();
+
+    for call in state.checked.symbols.symbols_in_file(&span.file) {
+        if !matches!(call.kind, SymbolKind::Call { .. }) || !contains(span, call.location) {
+            continue;
+        }
+
+        let callee = match callable_of_symbol(state, call) {
+            Ok(Some(callee)) => callee,
+            Ok(None) => continue,
+            Err(err) => {
+                log::debug!("could not resolve call at {}: {}", call.location, err.message());
+                continue;
+            }
+        };
+
+        let callee_location = package.tcx.callable_of(callee)?.name().location;
+        let from_range = state.lsp_range_of(call.location);
+
+        if let Some((_, existing)) = calls.iter_mut().find(|(callee, _)| *callee == callee_location) {
+            existing.from_ranges.push(from_range);
+            continue;
+        }
+
+        calls.push((callee_location, CallHierarchyOutgoingCall {
+            to: call_hierarchy_item(state, call.location, callee)?,
+            from_ranges: vec![from_range],
+        }));
+    }
+
+    Ok(calls.into_iter().map(|(_, call)| call).collect())
+}
+
+/// Builds the call hierarchy item of the given callable, where `location` is
+/// used to determine which package to resolve the callable in.
+fn call_hierarchy_item(state: &State, location: Location, reference: CallReference) -> Result<CallHierarchyItem> {
+    let Some(package) = state.package_of(location.file.package) else {
+        return Err(SimpleDiagnostic::new(format!("package of {location} is missing from the package graph")).into());
+    };
+
+    let callable = package.tcx.callable_of(reference)?;
+
+    let name_location = callable.name().location;
+    let span = declaration_span(state, location, reference).unwrap_or(name_location);
+
+    let kind = match reference {
+        CallReference::Function(_) => LspSymbolKind::FUNCTION,
+        CallReference::Method(_) => LspSymbolKind::METHOD,
+    };
+
+    let Some(name_location_lsp) = state.lsp_location_of(name_location) else {
+        return Err(SimpleDiagnostic::new(format!("could not create URI of {name_location}")).into());
+    };
+
+    Ok(CallHierarchyItem {
+        name: callable.name().to_string(),
+        kind,
+        tags: None,
+        detail: signature_of_callable(state, location, reference).ok(),
+        uri: name_location_lsp.uri,
+        range: state.lsp_range_of(span),
+        selection_range: state.lsp_range_of(name_location),
+        data: None,
+    })
+}
+
+/// Gets the callable which is referenced by the given symbol, either by being
+/// declared or invoked by it.
+fn callable_of_symbol(state: &State, sym: &SymbolEntry) -> Result<Option<CallReference>> {
+    let Some(package) = state.checked.graph.packages.get(&sym.location.file.package) else {
+        return Ok(None);
+    };
+
+    match &sym.kind {
+        SymbolKind::Callable { reference } => Ok(Some(*reference)),
+        SymbolKind::Call { id } => {
+            let Some(expr) = package.tcx.hir_call_expr(*id) else {
+                return Ok(None);
+            };
+
+            Ok(Some(package.tcx.probe_callable(expr)?.to_call_reference()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Finds the innermost callable whose declaration contains the given location,
+/// returning the location of its name along with the callable itself.
+fn enclosing_callable(state: &State, location: Location) -> Option<(Location, CallReference)> {
+    state
+        .checked
+        .symbols
+        .symbols_in_file(&location.file)
+        .filter_map(|sym| match sym.kind {
+            SymbolKind::Callable { reference } => {
+                let span = declaration_span(state, sym.location, reference)?;
+
+                contains(span, location).then_some((sym.location, reference, span.index.len()))
+            }
+            _ => None,
+        })
+        .min_by_key(|(_, _, len)| *len)
+        .map(|(name, reference, _)| (name, reference))
+}
+
+/// Gets the location of the entire declaration of the given callable.
+fn declaration_span(state: &State, location: Location, reference: CallReference) -> Option<Location> {
+    let package = state.checked.graph.packages.get(&location.file.package)?;
+
+    package.tcx.hir_node(node_id_of(reference)).map(|node| node.location())
+}
+
+fn node_id_of(reference: CallReference) -> NodeId {
+    match reference {
+        CallReference::Function(id) | CallReference::Method(id) => id,
+    }
+}
+
+fn contains(outer: Location, inner: Location) -> bool {
+    outer.file.id == inner.file.id && outer.index.start <= inner.index.start && outer.index.end >= inner.index.end
+}
@@ -0,0 +1,89 @@
+use lsp_types::{Color, ColorInformation, ColorPresentation, Range, TextEdit};
+use lume_span::SourceFile;
+
+use crate::position::position_from_range;
+use crate::state::State;
+
+/// Finds all colors within the given file.
+///
+/// Lume has no dedicated color literals, so colors are recognized by the
+/// common convention of hexadecimal color codes within string literals, such
+/// as `"#ff8800"` or `"#ff8800cc"`.
+pub(crate) fn document_colors(state: &State, file: &SourceFile) -> Vec<ColorInformation> {
+    let content = file.content.as_str();
+    let mut colors = Vec::new();
+
+    for (start, _) in content.match_indices("\"#") {
+        let code_start = start + 1;
+
+        let Some(code_len) = content[code_start..].find('"') else {
+            continue;
+        };
+
+        let code_end = code_start + code_len;
+
+        let Some(color) = parse_hex_color(&content[code_start..code_end]) else {
+            continue;
+        };
+
+        colors.push(ColorInformation {
+            range: position_from_range(content, &(code_start..code_end), state.position_encoding),
+            color,
+        });
+    }
+
+    colors
+}
+
+/// Gets the ways in which the given color can be written, to replace the text
+/// within the given range.
+pub(crate) fn color_presentations(color: Color, range: Range) -> Vec<ColorPresentation> {
+    let label = format_hex_color(color);
+
+    vec![ColorPresentation {
+        label: label.clone(),
+        text_edit: Some(TextEdit { range, new_text: label }),
+        additional_text_edits: None,
+    }]
+}
+
+/// Parses a color code in the form of `#rrggbb` or `#rrggbbaa`.
+fn parse_hex_color(code: &str) -> Option<Color> {
+    let hex = code.strip_prefix('#')?;
+
+    if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |idx: usize| {
+        u8::from_str_radix(&hex[idx..idx + 2], 16)
+            .ok()
+            .map(|value| f32::from(value) / 255.0)
+    };
+
+    Some(Color {
+        red: channel(0)?,
+        green: channel(2)?,
+        blue: channel(4)?,
+        alpha: if hex.len() == 8 { channel(6)? } else { 1.0 },
+    })
+}
+
+/// Formats the given color as `#rrggbb`, or `#rrggbbaa` if it is translucent.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn format_hex_color(color: Color) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    let mut code = format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.red),
+        channel(color.green),
+        channel(color.blue)
+    );
+
+    if color.alpha < 1.0 {
+        code.push_str(&format!("{:02x}", channel(color.alpha)));
+    }
+
+    code
+}
@@ -0,0 +1,557 @@
+use indexmap::IndexMap;
+use lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat, Uri};
+use lume_errors::Result;
+use lume_infer::query::CallReference;
+use lume_span::{Location, NodeId, SourceFile};
+
+use crate::position::index_from_position;
+use crate::state::State;
+use crate::symbols::hover::signature_of_callable;
+use crate::symbols::lookup::SymbolKind;
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Gets all completion items which are applicable at the given position within
+/// the given document.
+///
+/// The text preceding the cursor decides which kind of items are returned:
+/// - after a `.`, the fields and methods of the receiving expression;
+/// - after a `::`, the cases of the preceding enum type;
+/// - otherwise, all local variables, functions and types in scope.
+///
+/// Items are fuzzy-matched against the partial identifier under the cursor, if
+/// any, and ranked by how well they match. If completion was triggered by
+/// typing a single `:`, such as within a type annotation, no items are
+/// returned.
+///
+/// If completion is disabled within the configuration, no items are returned.
+pub(crate) fn completions_at(
+    state: &State,
+    uri: &Uri,
+    line: usize,
+    character: usize,
+    trigger_character: Option<&str>,
+) -> Vec<CompletionItem> {
+    if !state.config.enable_completion {
+        return Vec::new();
+    }
+
+    let Some(document) = state.vfs.get_document(uri) else {
+        return Vec::new();
+    };
+
+    let content = document.file.content.as_str();
+    let cursor = index_from_position(content, line, character, state.position_encoding);
+
+    let prefix_start = identifier_start(content, cursor);
+    let prefix = &content[prefix_start..cursor];
+    let preceding = &content[..prefix_start];
+
+    // Clients only support single-character triggers, so completion is triggered on
+    // each `:`, even though only `::` starts a path.
+    if trigger_character == Some(":") && !preceding.ends_with("::") {
+        return Vec::new();
+    }
+
+    let items = if let Some(path) = preceding.strip_suffix("::") {
+        let type_name = &path[path_start(path, path.len())..];
+
+        path_completions(state, type_name)
+    } else if let Some(receiver) = preceding.strip_suffix('.') {
+        member_completions(state, uri, receiver.len().saturating_sub(1))
+    } else {
+        scope_completions(state, &document.file, cursor)
+    };
+
+    let mut items = items
+        .into_iter()
+        .filter_map(|item| Some((match_score(&item.label, prefix)?, item)))
+        .collect::<Vec<_>>();
+
+    // Sorting is stable, so items with the same score keep their order, such as
+    // local variables before declarations.
+    items.sort_by(|(lhs, _), (rhs, _)| rhs.cmp(lhs));
+
+    // Clients sort items by their label by default, so the ranking must be encoded
+    // within the sort text to be retained.
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (_, item))| CompletionItem {
+            sort_text: Some(format!("{rank:05}")),
+            ..item
+        })
+        .collect()
+}
+
+/// Score of labels which start with the typed identifier, which always rank
+/// above all other matches.
+const PREFIX_MATCH_SCORE: u32 = 1 << 16;
+
+/// Scores how well the given label matches the partial identifier typed by
+/// the user, where higher scores are better matches.
+///
+/// Labels match if they contain all characters of the identifier in order,
+/// ignoring case, such as `tsr` matching `toString`. Characters which start a
+/// word within the label, or which directly follow the previously matched
+/// character, score higher. If the label doesn't match, [`None`] is returned.
+fn match_score(label: &str, pattern: &str) -> Option<u32> {
+    if label.starts_with(pattern) {
+        return Some(PREFIX_MATCH_SCORE * 2);
+    }
+
+    if label.to_lowercase().starts_with(&pattern.to_lowercase()) {
+        return Some(PREFIX_MATCH_SCORE);
+    }
+
+    let mut label_chars = label.chars();
+    let mut previous: Option<char> = None;
+    let mut is_consecutive = false;
+    let mut score = 0;
+
+    for pattern_char in pattern.chars() {
+        loop {
+            let label_char = label_chars.next()?;
+
+            let is_word_start = match previous {
+                None => true,
+                Some(previous) => previous == '_' || (previous.is_lowercase() && label_char.is_uppercase()),
+            };
+
+            previous = Some(label_char);
+
+            if label_char.to_lowercase().eq(pattern_char.to_lowercase()) {
+                score += 1;
+
+                if is_word_start {
+                    score += 8;
+                }
+
+                if is_consecutive {
+                    score += 4;
+                }
+
+                is_consecutive = true;
+                break;
+            }
+
+            is_consecutive = false;
+        }
+    }
+
+    Some(score)
+}
+
+/// Gets the byte index of the start of the identifier which ends at `end`.
+fn identifier_start(content: &str, end: usize) -> usize {
+    content[..end]
+        .char_indices()
+        .rev()
+        .find(|(_, ch)| !is_identifier_char(*ch))
+        .map_or(0, |(idx, ch)| idx + ch.len_utf8())
+}
+
+/// Gets the byte index of the start of the path which ends at `end`, such as
+/// `std::Color`, including all of its segments.
+fn path_start(content: &str, end: usize) -> usize {
+    content[..end]
+        .char_indices()
+        .rev()
+        .find(|(_, ch)| !is_identifier_char(*ch) && *ch != ':')
+        .map_or(0, |(idx, ch)| idx + ch.len_utf8())
+}
+
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Gets the fields and methods of the expression which ends at the given
+/// index.
+///
+/// If the type of the expression cannot be resolved, no items are returned.
+fn member_completions(state: &State, uri: &Uri, receiver_index: usize) -> Vec<CompletionItem> {
+    let Some(location) = state.location_at(uri, receiver_index) else {
+        return Vec::new();
+    };
+
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        return Vec::new();
+    };
+
+    let Some(package) = state.package_of(location.file.package) else {
+        return Vec::new();
+    };
+
+    let receiver_type = match &sym.kind {
+        SymbolKind::VariableReference { id } | SymbolKind::Call { id } | SymbolKind::Literal { id } => {
+            package.tcx.type_of(*id).map(Some)
+        }
+        SymbolKind::Member { callee, field } => package.tcx.type_of(*callee).map(|callee_type| {
+            package
+                .tcx
+                .tdb()
+                .find_field(callee_type.instance_of, &field.name)
+                .map(|field| field.field_type.clone())
+        }),
+        SymbolKind::Pattern { id } => match package.tcx.hir_node(*id) {
+            Some(lume_hir::Node::Pattern(pattern)) => package.tcx.type_of_pattern(pattern).map(Some),
+            _ => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    let receiver_type = match receiver_type {
+        Ok(Some(receiver_type)) => receiver_type,
+        Ok(None) => return Vec::new(),
+        Err(err) => {
+            log::debug!("could not resolve type of receiver: {}", err.message());
+            return Vec::new();
+        }
+    };
+
+    let mut items = Vec::new();
+
+    if let Some(lume_hir::Node::Type(lume_hir::TypeDefinition::Struct(struct_def))) =
+        package.tcx.hir_node(receiver_type.instance_of)
+    {
+        items.extend(struct_def.fields.iter().map(|field| {
+            completion_item(
+                field.name.to_string(),
+                CompletionItemKind::FIELD,
+                Some(field.field_type.to_string()),
+            )
+        }));
+    }
+
+    // Methods can be declared in any package, so all implementations within the
+    // workspace must be searched.
+    for package in state.checked.graph.packages.values() {
+        for node in package.tcx.hir().nodes().values() {
+            let (target, methods) = match node {
+                lume_hir::Node::Impl(type_impl) => (&type_impl.target, &type_impl.methods),
+                lume_hir::Node::TraitImpl(trait_impl) => (&trait_impl.target, &trait_impl.methods),
+                _ => continue,
+            };
+
+            let target_id = package.tcx.tdb().find_type(&target.name).map(|ty| ty.id);
+            if target_id != Some(receiver_type.instance_of) {
+                continue;
+            }
+
+            items.extend(methods.iter().map(|method| {
+                let reference = CallReference::Method(method.id);
+                let detail = signature_of_callable(state, method.name.location, reference).ok();
+
+                let item = completion_item(method.name.to_string(), CompletionItemKind::METHOD, detail);
+                let parameters = method.parameters.iter().map(|param| param.name.to_string());
+
+                with_call_snippet(state, item, parameters)
+            }));
+        }
+    }
+
+    items
+}
+
+/// Gets the cases of the enum with the given path, which may either be the
+/// name of the enum or any qualified path which ends with it.
+///
+/// Cases with associated data insert a placeholder for each of their
+/// parameters. If the path doesn't name any enum, no items are returned.
+fn path_completions(state: &State, type_path: &str) -> Vec<CompletionItem> {
+    for package in state.checked.graph.packages.values() {
+        for node in package.tcx.hir().nodes().values() {
+            let lume_hir::Node::Type(lume_hir::TypeDefinition::Enum(enum_def)) = node else {
+                continue;
+            };
+
+            let qualified_name = format!("{:+}", enum_def.name);
+            let is_match = enum_def.name.to_string() == type_path
+                || qualified_name == type_path
+                || qualified_name.ends_with(&format!("::{type_path}"));
+
+            if !is_match {
+                continue;
+            }
+
+            return enum_def
+                .cases
+                .iter()
+                .map(|case| {
+                    let parameters = case
+                        .parameters
+                        .iter()
+                        .map(std::string::ToString::to_string)
+                        .collect::<Vec<String>>();
+
+                    let detail = if parameters.is_empty() {
+                        format!("{:+}", case.name)
+                    } else {
+                        format!("{:+}({})", case.name, parameters.join(", "))
+                    };
+
+                    let item = completion_item(case.name.to_string(), CompletionItemKind::ENUM_MEMBER, Some(detail));
+
+                    if parameters.is_empty() {
+                        item
+                    } else {
+                        with_call_snippet(state, item, parameters.into_iter())
+                    }
+                })
+                .collect();
+        }
+    }
+
+    Vec::new()
+}
+
+/// Gets all local variables, functions and types which are in scope at the
+/// given index.
+fn scope_completions(state: &State, file: &SourceFile, cursor: usize) -> Vec<CompletionItem> {
+    let mut items = local_completions(state, file, cursor);
+    items.extend(declaration_completions(state));
+
+    items
+}
+
+/// Gets all parameters and local variables of the callable surrounding the
+/// cursor, which are declared before the cursor.
+///
+/// Since the HIR does not retain the extent of blocks, variables declared in
+/// blocks which have already been closed are also included.
+fn local_completions(state: &State, file: &SourceFile, cursor: usize) -> Vec<CompletionItem> {
+    let Some(package) = state.checked.graph.packages.get(&file.package) else {
+        return Vec::new();
+    };
+
+    let mut visitor = LocalsVisitor::default();
+    if let Err(err) = traverse(package.tcx.hir(), &mut visitor) {
+        log::error!("could not collect local variables: {}", err.message());
+        return Vec::new();
+    }
+
+    let precedes_cursor = |location: &Location| location.file.id == file.id && location.index.start < cursor;
+
+    // The callable which contains the cursor is the last callable declared
+    // before the cursor.
+    let Some(scope) = visitor
+        .callables
+        .iter()
+        .filter(|callable| precedes_cursor(&callable.location))
+        .max_by_key(|callable| callable.location.index.start)
+    else {
+        return Vec::new();
+    };
+
+    let mut locals = IndexMap::new();
+
+    for (name, param_type) in &scope.parameters {
+        let item = completion_item(name.clone(), CompletionItemKind::VARIABLE, Some(param_type.clone()));
+        locals.insert(name.clone(), item);
+    }
+
+    let mut variables = visitor
+        .variables
+        .iter()
+        .filter(|var| precedes_cursor(&var.location) && var.location.index.start > scope.location.index.start)
+        .collect::<Vec<_>>();
+
+    variables.sort_by_key(|var| var.location.index.start);
+
+    // Variables which are declared later shadow earlier declarations with the
+    // same name.
+    for variable in variables {
+        let detail = match &variable.declared_type {
+            Some(declared_type) => Some(declared_type.clone()),
+            None => package
+                .tcx
+                .type_of(variable.value)
+                .and_then(|ty| package.tcx.new_named_type(&ty, true))
+                .ok()
+                .map(|name| name.to_string()),
+        };
+
+        let item = completion_item(variable.name.clone(), CompletionItemKind::VARIABLE, detail);
+        locals.insert(variable.name.clone(), item);
+    }
+
+    locals.into_values().collect()
+}
+
+/// Gets all top-level functions and types within the workspace.
+fn declaration_completions(state: &State) -> Vec<CompletionItem> {
+    let mut items = IndexMap::new();
+
+    for package in state.checked.graph.packages.values() {
+        for node in package.tcx.hir().nodes().values() {
+            let item = match node {
+                lume_hir::Node::Function(func) => {
+                    let reference = CallReference::Function(func.id);
+                    let detail = signature_of_callable(state, func.name.location, reference).ok();
+
+                    let item = completion_item(func.name.to_string(), CompletionItemKind::FUNCTION, detail);
+                    let parameters = func.parameters.iter().map(|param| param.name.to_string());
+
+                    with_call_snippet(state, item, parameters)
+                }
+                lume_hir::Node::Type(type_def) => {
+                    let (name, kind) = match type_def {
+                        lume_hir::TypeDefinition::Struct(struct_def) => (&struct_def.name, CompletionItemKind::STRUCT),
+                        lume_hir::TypeDefinition::Trait(trait_def) => (&trait_def.name, CompletionItemKind::INTERFACE),
+                        lume_hir::TypeDefinition::Enum(enum_def) => (&enum_def.name, CompletionItemKind::ENUM),
+                    };
+
+                    completion_item(name.to_string(), kind, Some(format!("{name:+}")))
+                }
+                _ => continue,
+            };
+
+            items.entry(item.label.clone()).or_insert(item);
+        }
+    }
+
+    items.into_values().collect()
+}
+
+fn completion_item(label: String, kind: CompletionItemKind, detail: Option<String>) -> CompletionItem {
+    CompletionItem {
+        insert_text: Some(label.clone()),
+        label,
+        kind: Some(kind),
+        detail,
+        ..Default::default()
+    }
+}
+
+/// Makes the given completion item of a callable insert a call to it, with a
+/// placeholder for each of the given parameters.
+///
+/// If the client doesn't support snippets, the item is returned as-is, so only
+/// the name of the callable is inserted.
+fn with_call_snippet(
+    state: &State,
+    mut item: CompletionItem,
+    parameters: impl Iterator<Item = String>,
+) -> CompletionItem {
+    if !state.snippet_support {
+        return item;
+    }
+
+    // The receiver of a method is passed implicitly, so it has no placeholder.
+    let placeholders = parameters
+        .filter(|name| name != "self")
+        .enumerate()
+        .map(|(idx, name)| format!("${{{}:{name}}}", idx + 1))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    item.insert_text = Some(format!("{}({placeholders})", item.label));
+    item.insert_text_format = Some(InsertTextFormat::SNIPPET);
+
+    item
+}
+
+struct CallableScope {
+    location: Location,
+
+    /// Name and type of each parameter of the callable.
+    parameters: Vec<(String, String)>,
+}
+
+struct LocalVariable {
+    name: String,
+    location: Location,
+    declared_type: Option<String>,
+    value: NodeId,
+}
+
+#[derive(Default)]
+struct LocalsVisitor {
+    callables: Vec<CallableScope>,
+    variables: Vec<LocalVariable>,
+}
+
+impl Visitor for LocalsVisitor {
+    fn visit_node(&mut self, node: &lume_hir::Node) -> Result<()> {
+        let (location, parameters) = match node {
+            lume_hir::Node::Function(func) => (func.name.location, &func.parameters),
+            lume_hir::Node::Method(method) => (method.name.location, &method.parameters),
+            lume_hir::Node::TraitMethodDef(method) => (method.name.location, &method.parameters),
+            lume_hir::Node::TraitMethodImpl(method) => (method.name.location, &method.parameters),
+            _ => return Ok(()),
+        };
+
+        let parameters = parameters
+            .iter()
+            .map(|param| (param.name.to_string(), param.param_type.to_string()))
+            .collect();
+
+        self.callables.push(CallableScope { location, parameters });
+
+        Ok(())
+    }
+
+    fn visit_stmt(&mut self, stmt: &lume_hir::Statement) -> Result<()> {
+        if let lume_hir::StatementKind::Variable(decl) = &stmt.kind {
+            self.variables.push(LocalVariable {
+                name: decl.name.to_string(),
+                location: decl.name.location,
+                declared_type: decl.declared_type.as_ref().map(ToString::to_string),
+                value: decl.value,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ranks the given labels against the pattern, like completion items are
+    /// ranked, skipping labels which don't match.
+    fn rank<'a>(labels: &[&'a str], pattern: &str) -> Vec<&'a str> {
+        let mut scored = labels
+            .iter()
+            .filter_map(|label| Some((match_score(label, pattern)?, *label)))
+            .collect::<Vec<_>>();
+
+        scored.sort_by(|(lhs, _), (rhs, _)| rhs.cmp(lhs));
+        scored.into_iter().map(|(_, label)| label).collect()
+    }
+
+    #[test]
+    fn prefix_matches_rank_highest() {
+        let ranked = rank(&["matchToString", "ToString", "toString", "tsr"], "to");
+
+        assert_eq!(ranked[0], "toString");
+        assert_eq!(ranked[1], "ToString");
+    }
+
+    #[test]
+    fn word_starts_rank_above_other_characters() {
+        let ranked = rank(&["testStringRepr", "toString", "tokenStr"], "tS");
+
+        assert_eq!(ranked, vec!["toString", "tokenStr", "testStringRepr"]);
+    }
+
+    #[test]
+    fn consecutive_characters_rank_higher() {
+        let consecutive = match_score("evaluate", "val").unwrap();
+        let scattered = match_score("xvxaxl", "val").unwrap();
+
+        assert!(consecutive > scattered);
+    }
+
+    #[test]
+    fn characters_must_match_in_order() {
+        assert!(match_score("toString", "tsr").is_some());
+        assert_eq!(match_score("toString", "rst"), None);
+        assert_eq!(match_score("abc", "abcd"), None);
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        assert_eq!(match_score("anything", ""), Some(PREFIX_MATCH_SCORE * 2));
+    }
+}
@@ -0,0 +1,225 @@
+use lume_errors::Result;
+use lume_hir::WithLocation as _;
+use lume_span::Location;
+
+use crate::state::State;
+use crate::symbols::lookup::{SymbolEntry, SymbolKind};
+
+/// Attempts to find the location of the declaration of the symbol at the given
+/// location.
+///
+/// If no symbol exists at the given location or if the symbol has no
+/// declaration, such as literals, returns [`None`].
+pub(crate) fn definition_of(state: &State, location: Location) -> Result<Option<Location>> {
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        log::warn!("could not find matching node for {location}");
+        return Ok(None);
+    };
+
+    definition_of_symbol(state, sym)
+}
+
+/// Attempts to find the location of the declaration of the given symbol.
+pub(crate) fn definition_of_symbol(state: &State, sym: &SymbolEntry) -> Result<Option<Location>> {
+    let Some(package) = state.package_of(sym.location.file.package) else {
+        return Ok(None);
+    };
+
+    match &sym.kind {
+        SymbolKind::Type { name } => {
+            let Some(type_id) = package.tcx.tdb().find_type(name).map(|ty| ty.id) else {
+                // Type parameters are not registered as types, so they are resolved from the
+                // declaration which encloses the reference instead.
+                if !name.root.is_empty() {
+                    return Ok(None);
+                }
+
+                let type_param = type_parameter_at(package.tcx.hir(), sym.location, &name.name.name().name);
+
+                return Ok(type_param.map(|type_param| type_param.name.location));
+            };
+
+            let Some(lume_hir::Node::Type(type_def)) = package.tcx.hir_node(type_id) else {
+                return Ok(None);
+            };
+
+            Ok(Some(location_of_type_def(type_def)))
+        }
+        SymbolKind::Callable { reference } => {
+            let callable = package.tcx.callable_of(*reference)?;
+
+            Ok(Some(callable.name().location))
+        }
+        SymbolKind::Call { id } => {
+            let Some(expr) = package.tcx.hir_call_expr(*id) else {
+                return Ok(None);
+            };
+
+            let callable = package.tcx.probe_callable(expr)?;
+
+            Ok(Some(callable.name().location))
+        }
+        SymbolKind::Variant { name } => {
+            let enum_case = package.tcx.enum_case_with_name(name)?;
+
+            Ok(Some(enum_case.name.location()))
+        }
+        SymbolKind::Field { id } => {
+            let Some(lume_hir::Node::Field(field)) = package.tcx.hir_node(*id) else {
+                return Ok(None);
+            };
+
+            Ok(Some(field.name.location))
+        }
+        SymbolKind::Member { callee, field } => {
+            let callee_type = package.tcx.type_of(*callee)?;
+            let Some(field) = package.tcx.tdb().find_field(callee_type.instance_of, &field.name) else {
+                return Ok(None);
+            };
+
+            let Some(lume_hir::Node::Field(field)) = package.tcx.hir_node(field.id) else {
+                return Ok(None);
+            };
+
+            Ok(Some(field.name.location))
+        }
+        SymbolKind::VariableReference { id } => {
+            let Some(lume_hir::ExpressionKind::Variable(variable_ref)) = package.tcx.hir_expr(*id).map(|e| &e.kind)
+            else {
+                return Ok(None);
+            };
+
+            let declaration = match &variable_ref.reference {
+                lume_hir::VariableSource::Variable(var_decl) => var_decl.name.location,
+                lume_hir::VariableSource::Parameter(param) => param.name.location,
+                lume_hir::VariableSource::Pattern(pattern) => pattern.location,
+            };
+
+            Ok(Some(declaration))
+        }
+        SymbolKind::Pattern { .. } | SymbolKind::TypeParameter { .. } => Ok(Some(sym.location)),
+        SymbolKind::Literal { .. } => Ok(None),
+    }
+}
+
+/// Attempts to find the location of the declaration of the type of the symbol
+/// at the given location.
+///
+/// If no symbol exists at the given location or if the type has no declaration
+/// within the HIR, such as builtin types, returns [`None`].
+pub(crate) fn type_definition_of(state: &State, location: Location) -> Result<Option<Location>> {
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        log::warn!("could not find matching node for {location}");
+        return Ok(None);
+    };
+
+    let Some(package) = state.package_of(sym.location.file.package) else {
+        return Ok(None);
+    };
+
+    let type_id = match &sym.kind {
+        // Types and variants are their own type, so they resolve to the same
+        // declaration as go-to-definition.
+        SymbolKind::Type { .. } => return definition_of_symbol(state, sym),
+        SymbolKind::Variant { name } => {
+            let Some(enum_name) = name.clone().parent() else {
+                return Ok(None);
+            };
+
+            package.tcx.enum_def_of_name(&enum_name)?.id
+        }
+        SymbolKind::VariableReference { id } | SymbolKind::Call { id } | SymbolKind::Literal { id } => {
+            package.tcx.type_of(*id)?.instance_of
+        }
+        SymbolKind::Pattern { id } => {
+            let Some(lume_hir::Node::Pattern(pattern)) = package.tcx.hir_node(*id) else {
+                return Ok(None);
+            };
+
+            package.tcx.type_of_pattern(pattern)?.instance_of
+        }
+        SymbolKind::Field { id } => {
+            let Some(lume_hir::Node::Field(field)) = package.tcx.hir_node(*id) else {
+                return Ok(None);
+            };
+
+            let struct_def = package.tcx.owning_struct_of_field(*id)?;
+
+            package
+                .tcx
+                .mk_type_ref_from(&field.field_type, struct_def.id)?
+                .instance_of
+        }
+        SymbolKind::Member { callee, field } => {
+            let callee_type = package.tcx.type_of(*callee)?;
+            let Some(field) = package.tcx.tdb().find_field(callee_type.instance_of, &field.name) else {
+                return Ok(None);
+            };
+
+            field.field_type.instance_of
+        }
+        SymbolKind::Callable { .. } | SymbolKind::TypeParameter { .. } => return Ok(None),
+    };
+
+    let Some(lume_hir::Node::Type(type_def)) = package.tcx.hir_node(type_id) else {
+        return Ok(None);
+    };
+
+    Ok(Some(location_of_type_def(type_def)))
+}
+
+/// Gets the location of the name of the given type definition.
+pub(crate) fn location_of_type_def(type_def: &lume_hir::TypeDefinition) -> Location {
+    match type_def {
+        lume_hir::TypeDefinition::Struct(struct_def) => struct_def.name().location(),
+        lume_hir::TypeDefinition::Trait(trait_def) => trait_def.name().location(),
+        lume_hir::TypeDefinition::Enum(enum_def) => enum_def.name().location(),
+    }
+}
+
+/// Finds the declaration of the type parameter with the given name, which is in
+/// scope at the given location.
+///
+/// Type parameters of methods shadow the type parameters of their enclosing
+/// implementation or type, so the innermost declaration which contains the
+/// location is used.
+pub(crate) fn type_parameter_at<'hir>(
+    hir: &'hir lume_hir::Map,
+    location: Location,
+    name: &str,
+) -> Option<&'hir lume_hir::TypeParameter> {
+    hir.nodes()
+        .values()
+        .filter(|node| {
+            let span = node.location();
+
+            span.file.id == location.file.id
+                && span.index.start <= location.index.start
+                && span.index.end >= location.index.end
+        })
+        .filter_map(|node| {
+            let type_param = type_parameters_of_node(node).find(|type_param| type_param.name.name == name)?;
+
+            Some((type_param, node.location().index.len()))
+        })
+        .min_by_key(|(_, len)| *len)
+        .map(|(type_param, _)| type_param)
+}
+
+/// Gets the type parameters which are declared on the given node, if any.
+fn type_parameters_of_node(node: &lume_hir::Node) -> Box<dyn Iterator<Item = &lume_hir::TypeParameter> + '_> {
+    match node {
+        lume_hir::Node::Function(func) => Box::new(func.type_parameters.iter()),
+        lume_hir::Node::Method(method) => Box::new(method.type_parameters.iter()),
+        lume_hir::Node::TraitMethodDef(method) => Box::new(method.type_parameters.iter()),
+        lume_hir::Node::TraitMethodImpl(method) => Box::new(method.type_parameters.iter()),
+        lume_hir::Node::Impl(type_impl) => Box::new(type_impl.type_parameters.iter()),
+        lume_hir::Node::TraitImpl(trait_impl) => Box::new(trait_impl.type_parameters.iter()),
+        lume_hir::Node::Type(lume_hir::TypeDefinition::Struct(struct_def)) => {
+            Box::new(struct_def.type_parameters.iter())
+        }
+        lume_hir::Node::Type(lume_hir::TypeDefinition::Trait(trait_def)) => Box::new(trait_def.type_parameters.iter()),
+        lume_hir::Node::Type(lume_hir::TypeDefinition::Enum(enum_def)) => Box::new(enum_def.type_parameters.iter()),
+        _ => Box::new(std::iter::empty()),
+    }
+}
@@ -0,0 +1,243 @@
+use lsp_types::{FoldingRange, FoldingRangeKind};
+use lume_errors::Result;
+use lume_hir::WithLocation as _;
+use lume_span::{Location, SourceFile};
+
+use crate::position::{PositionEncoding, position_from_index};
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Gets all foldable regions within the given file, such as declarations,
+/// loops and conditional blocks, as well as the imports at the top of the
+/// file and comments which span multiple lines.
+pub(crate) fn folding_ranges_of(
+    hir: &lume_hir::Map,
+    file: &SourceFile,
+    encoding: PositionEncoding,
+) -> Result<Vec<FoldingRange>> {
+    let mut visitor = FoldingVisitor {
+        file,
+        locations: Vec::new(),
+    };
+
+    traverse(hir, &mut visitor)?;
+
+    let mut ranges = Vec::new();
+
+    if let Some(imports) = import_range(&file.content) {
+        ranges.push(imports);
+    }
+
+    ranges.extend(comment_ranges(&file.content));
+
+    for location in visitor.locations {
+        let start = position_from_index(&file.content, location.index.start, encoding);
+        let end = position_from_index(&file.content, location.index.end, encoding);
+
+        // Keep the line with the closing brace visible when the region is folded.
+        let end_line = end.line.saturating_sub(1);
+        if end_line <= start.line {
+            continue;
+        }
+
+        ranges.push(FoldingRange {
+            start_line: start.line,
+            start_character: None,
+            end_line,
+            end_character: None,
+            kind: Some(FoldingRangeKind::Region),
+            collapsed_text: None,
+        });
+    }
+
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    ranges.dedup_by_key(|range| (range.start_line, range.end_line));
+
+    Ok(ranges)
+}
+
+/// Gets the range of the import statements at the top of the file, if there
+/// are more than one.
+fn import_range(content: &str) -> Option<FoldingRange> {
+    let (start_line, end_line) = import_lines(content)?;
+    if end_line <= start_line {
+        return None;
+    }
+
+    Some(FoldingRange {
+        start_line: u32::try_from(start_line).ok()?,
+        start_character: None,
+        end_line: u32::try_from(end_line).ok()?,
+        end_character: None,
+        kind: Some(FoldingRangeKind::Imports),
+        collapsed_text: None,
+    })
+}
+
+/// Gets the first and last line of the import statements at the top of the
+/// file, if there are any.
+///
+/// Imports are resolved when lowering into HIR, so they are found by scanning
+/// the source text instead.
+pub(crate) fn import_lines(content: &str) -> Option<(usize, usize)> {
+    let mut start_line = None;
+    let mut end_line = 0;
+
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.starts_with("import ") {
+            start_line.get_or_insert(idx);
+            end_line = idx;
+        } else if !(line.is_empty() || line.starts_with("//")) {
+            break;
+        }
+    }
+
+    Some((start_line?, end_line))
+}
+
+/// Gets the ranges of all runs of consecutive line comments and all block
+/// comments within the given content, which span more than a single line.
+///
+/// Comments are discarded when lowering into HIR, so they are found by scanning
+/// the source text instead. String literals are skipped, so comment markers
+/// within them are not mistaken for comments.
+fn comment_ranges(content: &str) -> Vec<FoldingRange> {
+    let mut lines = Vec::new();
+
+    let mut line = 0;
+    let mut line_comment_run: Option<(usize, usize)> = None;
+    let mut line_has_code = false;
+
+    let mut chars = content.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\n' => {
+                line += 1;
+                line_has_code = false;
+            }
+            '"' => {
+                line_has_code = true;
+
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '\n' => line += 1,
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                // Comments trailing after code are not part of a comment block.
+                if !line_has_code {
+                    line_comment_run = match line_comment_run {
+                        Some((start, end)) if end + 1 == line => Some((start, line)),
+                        run => {
+                            lines.extend(run);
+                            Some((line, line))
+                        }
+                    };
+                }
+
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        line += 1;
+                        line_has_code = false;
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                let start = line;
+                let mut prev = '\0';
+
+                for ch in chars.by_ref() {
+                    match ch {
+                        '\n' => line += 1,
+                        '/' if prev == '*' => break,
+                        _ => {}
+                    }
+
+                    prev = ch;
+                }
+
+                lines.push((start, line));
+            }
+            ch if !ch.is_whitespace() => line_has_code = true,
+            _ => {}
+        }
+    }
+
+    lines.extend(line_comment_run);
+
+    lines
+        .into_iter()
+        .filter(|(start, end)| end > start)
+        .filter_map(|(start, end)| {
+            Some(FoldingRange {
+                start_line: u32::try_from(start).ok()?,
+                start_character: None,
+                end_line: u32::try_from(end).ok()?,
+                end_character: None,
+                kind: Some(FoldingRangeKind::Comment),
+                collapsed_text: None,
+            })
+        })
+        .collect()
+}
+
+/// Collects the locations of all foldable constructs within a single file.
+struct FoldingVisitor<'a> {
+    file: &'a SourceFile,
+    locations: Vec<Location>,
+}
+
+impl FoldingVisitor<'_> {
+    fn push(&mut self, location: Location) {
+        if location.file.id == self.file.id {
+            self.locations.push(location);
+        }
+    }
+}
+
+impl Visitor for FoldingVisitor<'_> {
+    fn visit_node(&mut self, node: &lume_hir::Node) -> Result<()> {
+        match node {
+            lume_hir::Node::Function(_)
+            | lume_hir::Node::Type(_)
+            | lume_hir::Node::Impl(_)
+            | lume_hir::Node::TraitImpl(_)
+            | lume_hir::Node::Method(_)
+            | lume_hir::Node::TraitMethodDef(_)
+            | lume_hir::Node::TraitMethodImpl(_) => self.push(node.location()),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn visit_stmt(&mut self, stmt: &lume_hir::Statement) -> Result<()> {
+        if let lume_hir::StatementKind::InfiniteLoop(_) | lume_hir::StatementKind::IteratorLoop(_) = &stmt.kind {
+            self.push(stmt.location);
+        }
+
+        Ok(())
+    }
+
+    fn visit_expr(&mut self, expr: &lume_hir::Expression) -> Result<()> {
+        if let lume_hir::ExpressionKind::If(_)
+        | lume_hir::ExpressionKind::Switch(_)
+        | lume_hir::ExpressionKind::Scope(_) = &expr.kind
+        {
+            self.push(expr.location);
+        }
+
+        Ok(())
+    }
+}
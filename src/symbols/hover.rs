@@ -1,18 +1,89 @@
+use std::sync::Mutex;
+
+use indexmap::IndexMap;
 use lume_errors::Result;
 use lume_hir::Identifier;
 use lume_infer::query::CallReference;
-use lume_span::{Location, NodeId};
+use lume_span::{Location, NodeId, PackageId, SourceFileId};
 
 use crate::state::State;
+use crate::symbols::definition::type_parameter_at;
 use crate::symbols::lookup::SymbolKind;
+use crate::symbols::type_hierarchy::implemented_traits_of;
+
+/// Maximum number of positions whose hover content is cached.
+const HOVER_CACHE_CAPACITY: usize = 64;
+
+/// Least-recently-used cache of hover content, keyed by the hovered position
+/// and the version of the graph it was computed from.
+///
+/// Hovering the same position repeatedly, such as when the mouse jitters,
+/// returns the cached content instead of querying the type context again.
+#[derive(Default)]
+pub(crate) struct HoverCache {
+    entries: Mutex<IndexMap<(SourceFileId, usize, u64), (String, Location)>>,
+}
+
+impl HoverCache {
+    /// Gets the cached content of the given key, marking it as the most
+    /// recently used entry.
+    fn get(&self, key: (SourceFileId, usize, u64)) -> Option<(String, Location)> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let content = entries.shift_remove(&key)?;
+        entries.insert(key, content.clone());
+
+        Some(content)
+    }
+
+    /// Inserts the given content, evicting the least recently used entry if
+    /// the cache is full.
+    fn insert(&self, key: (SourceFileId, usize, u64), content: (String, Location)) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= HOVER_CACHE_CAPACITY {
+            entries.shift_remove_index(0);
+        }
+
+        entries.insert(key, content);
+    }
+
+    /// Removes all cached content, such as when the graph is replaced.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// Gets the hover content of the symbol at the given location, along with the
+/// span of the hovered symbol, if any symbol exists at the location.
+///
+/// Content is cached per position until the graph is replaced by the next
+/// check of the workspace.
+pub(crate) fn hover_content_of(state: &State, location: Location) -> Result<Option<(String, Location)>> {
+    let key = (location.file.id, location.index.start, state.checked.version);
+
+    if let Some(content) = state.checked.hover_cache.get(key) {
+        return Ok(Some(content));
+    }
+
+    // Positions without any symbol are resolved quickly, so only actual content
+    // is cached.
+    let content = compute_hover_content_of(state, location)?;
 
-pub(crate) fn hover_content_of(state: &State, location: Location) -> Result<String> {
+    if let Some(content) = &content {
+        state.checked.hover_cache.insert(key, content.clone());
+    }
+
+    Ok(content)
+}
+
+fn compute_hover_content_of(state: &State, location: Location) -> Result<Option<(String, Location)>> {
     let Some(sym) = state.checked.symbols.lookup_position(location) else {
         log::warn!("could not find matching node for {location}");
-        return Ok(String::new());
+        return Ok(None);
     };
 
-    match &sym.kind {
+    let content = match &sym.kind {
         SymbolKind::Type { name } => hover_content_of_type(state, location, name),
         SymbolKind::Callable { reference } => hover_content_of_callable(state, location, *reference),
         SymbolKind::Variant { name } => hover_content_of_variant(state, location, name),
@@ -22,25 +93,76 @@ pub(crate) fn hover_content_of(state: &State, location: Location) -> Result<Stri
         SymbolKind::Literal { id } => hover_content_of_literal(state, location, *id),
         SymbolKind::Member { callee, field } => hover_content_of_member(state, location, *callee, field),
         SymbolKind::VariableReference { id } => hover_content_of_variable_ref(state, location, *id),
-    }
+        SymbolKind::TypeParameter { name } => Ok(hover_content_of_type_parameter(state, sym.location, &name.name)),
+    }?;
+
+    let content = match state.hover_content_format {
+        lsp_types::MarkupKind::PlainText => plain_text_of(&content),
+        lsp_types::MarkupKind::Markdown => content,
+    };
+
+    let span = match &sym.kind {
+        SymbolKind::Type { name } => segment_at(name, location.index.start).unwrap_or(sym.location),
+        _ => sym.location,
+    };
+
+    Ok(Some((content, span)))
+}
+
+/// Renders the given Markdown hover content as plain text, for clients which
+/// cannot render Markdown.
+///
+/// Code fences around signatures are removed entirely, while inline code only
+/// loses its backticks.
+pub(crate) fn plain_text_of(content: &str) -> String {
+    content
+        .lines()
+        .filter(|line| !line.starts_with("```"))
+        .map(|line| line.replace('`', ""))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Gets the span of the segment within the given path which contains the given
+/// index, so only the hovered segment of a multi-segment path is highlighted.
+fn segment_at(path: &lume_hir::Path, index: usize) -> Option<Location> {
+    path.root.iter().chain(std::iter::once(&path.name)).find_map(|segment| {
+        let location = segment.name().location;
+
+        (location.index.start <= index && index < location.index.end).then_some(location)
+    })
 }
 
 pub(crate) fn hover_content_of_type(state: &State, location: Location, type_name: &lume_hir::Path) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
     let Some(type_id) = package.tcx.tdb().find_type(type_name).map(|ty| ty.id) else {
+        if type_name.root.is_empty() {
+            return Ok(hover_content_of_type_parameter(
+                state,
+                location,
+                &type_name.name.name().name,
+            ));
+        }
+
         return Ok(String::new());
     };
 
-    let Some(lume_hir::Node::Type(type_def)) = package.tcx.hir_node(type_id) else {
+    // Types from dependencies are only declared within the HIR of their own
+    // package, so the declaration must be read from there.
+    let declaring_package_id = declaring_package_of(state, type_id, location.file.package);
+    let Some(package) = state.package_of(declaring_package_id) else {
         return Ok(String::new());
     };
 
-    let documentation = match package.tcx.documentation_string_of(type_id) {
-        Some(str) => format!("\n\n{str}"),
-        None => String::new(),
+    let Some(lume_hir::Node::Type(type_def)) = package.tcx.hir_node(type_id) else {
+        return Ok(hover_content_of_builtin_type(state, location, type_name, type_id));
     };
 
-    match type_def {
+    let documentation = documentation_of(state, location, type_id, None);
+
+    let (header, declared_name) = match type_def {
         lume_hir::TypeDefinition::Struct(struct_def) => {
             let builtin = if struct_def.builtin {
                 String::from("builtin ")
@@ -48,41 +170,154 @@ pub(crate) fn hover_content_of_type(state: &State, location: Location, type_name
                 String::new()
             };
 
-            Ok(format!(
-                "```lm\n{} struct {builtin}{:+}\n```{documentation}",
-                struct_def.visibility, struct_def.name
-            ))
+            let header = format!(
+                "{} struct {builtin}{:+}{}",
+                struct_def.visibility,
+                struct_def.name,
+                type_parameters_of(struct_def.type_parameters.iter())
+            );
+
+            (header, &struct_def.name)
+        }
+        lume_hir::TypeDefinition::Trait(trait_def) => {
+            let header = format!(
+                "{} trait {:+}{}",
+                trait_def.visibility,
+                trait_def.name,
+                type_parameters_of(trait_def.type_parameters.iter())
+            );
+
+            (header, &trait_def.name)
         }
-        lume_hir::TypeDefinition::Trait(trait_def) => Ok(format!(
-            "```lm\n{} trait {:+}\n```{documentation}",
-            trait_def.visibility, trait_def.name
-        )),
-        lume_hir::TypeDefinition::Enum(enum_def) => Ok(format!(
-            "```lm\n{} enum {:+}\n```{documentation}",
-            enum_def.visibility, enum_def.name
-        )),
+        lume_hir::TypeDefinition::Enum(enum_def) => {
+            let header = format!(
+                "{} enum {:+}{}",
+                enum_def.visibility,
+                enum_def.name,
+                type_parameters_of(enum_def.type_parameters.iter())
+            );
+
+            (header, &enum_def.name)
+        }
+    };
+
+    // When the hovered path instantiates the type with concrete arguments, show
+    // the instantiated form below the declaration.
+    let instantiation = match &type_name.name {
+        lume_hir::PathSegment::Type { type_arguments, .. } if !type_arguments.is_empty() => {
+            let type_arguments = type_arguments
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            format!("\n\n{declared_name:+}<{type_arguments}>")
+        }
+        _ => String::new(),
+    };
+
+    Ok(format!("```lm\n{header}{instantiation}\n```{documentation}"))
+}
+
+/// Renders the hover content of a builtin type, which has no declaration
+/// within the HIR, from its documentation and the traits it implements.
+fn hover_content_of_builtin_type(
+    state: &State,
+    location: Location,
+    type_name: &lume_hir::Path,
+    type_id: NodeId,
+) -> String {
+    let documentation = documentation_of(state, location, type_id, None);
+
+    let trait_names = implemented_traits_of(state, type_id)
+        .into_iter()
+        .filter_map(|trait_id| {
+            state
+                .checked
+                .graph
+                .packages
+                .values()
+                .find_map(|package| match package.tcx.hir_node(trait_id) {
+                    Some(lume_hir::Node::Type(lume_hir::TypeDefinition::Trait(trait_def))) => {
+                        Some(format!("`{:+}`", trait_def.name))
+                    }
+                    _ => None,
+                })
+        })
+        .collect::<Vec<String>>();
+
+    let implementations = if trait_names.is_empty() {
+        String::new()
+    } else {
+        format!("\n\nImplements {}", trait_names.join(", "))
+    };
+
+    format!("```lm\nbuiltin {type_name:+}\n```{documentation}{implementations}")
+}
+
+/// Renders the hover content of the type parameter with the given name, as it
+/// is declared on the generic which encloses the given location.
+fn hover_content_of_type_parameter(state: &State, location: Location, name: &str) -> String {
+    let Some(package) = state.package_of(location.file.package) else {
+        return String::new();
+    };
+
+    let Some(type_param) = type_parameter_at(package.tcx.hir(), location, name) else {
+        return String::new();
+    };
+
+    format!("```lm\n{}\n```", type_parameter_of(type_param))
+}
+
+/// Renders the given type parameters as they would be declared, including the
+/// trait constraints of each parameter. If there are no type parameters, an
+/// empty string is returned.
+fn type_parameters_of<'a>(type_parameters: impl Iterator<Item = &'a lume_hir::TypeParameter>) -> String {
+    let type_parameters = type_parameters.map(type_parameter_of).collect::<Vec<String>>();
+
+    if type_parameters.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", type_parameters.join(", "))
     }
 }
 
+/// Renders a single type parameter as it would be declared, such as
+/// `T: Constraint1 + Constraint2`.
+fn type_parameter_of(type_param: &lume_hir::TypeParameter) -> String {
+    if type_param.constraints.is_empty() {
+        return type_param.name.to_string();
+    }
+
+    let constraints = type_param
+        .constraints
+        .iter()
+        .map(std::string::ToString::to_string)
+        .collect::<Vec<String>>()
+        .join(" + ");
+
+    format!("{}: {constraints}", type_param.name)
+}
+
 pub(crate) fn hover_content_of_callable(state: &State, location: Location, reference: CallReference) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
     let callable = package.tcx.callable_of(reference)?;
 
-    let identifier = lume_hir::Identifier {
-        name: format!("{:+}", callable.name()),
-        location: callable.name().location,
+    let signature = signature_of_callable(state, location, reference)?;
+
+    let declaring_package_id = declaring_package_of(state, callable.id(), location.file.package);
+    let Some(declaring_package) = state.package_of(declaring_package_id) else {
+        return Ok(String::new());
     };
 
-    let signature = package.tcx.sig_to_string(&identifier, callable.signature(), false)?;
-    let visibility = match package.tcx.visibility_of(callable.id()) {
+    let visibility = match declaring_package.tcx.visibility_of(callable.id()) {
         Some(visibility) => format!("{visibility} "),
         None => String::new(),
     };
 
-    let documentation = match package.tcx.documentation_string_of(callable.id()) {
-        Some(str) => format!("\n\n{str}"),
-        None => String::new(),
-    };
+    let documentation = documentation_of(state, location, callable.id(), None);
 
     Ok(format!("```lm\n{visibility}{signature}\n```{documentation}"))
 }
@@ -93,7 +328,9 @@ pub(crate) fn hover_content_of_member(
     callee: NodeId,
     field: &Identifier,
 ) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
 
     let callee_type = package.tcx.type_of(callee)?;
     let Some(field) = package.tcx.tdb().find_field(callee_type.instance_of, &field.name) else {
@@ -102,10 +339,7 @@ pub(crate) fn hover_content_of_member(
 
     let field_type = package.tcx.new_named_type(&field.field_type, true)?;
 
-    let documentation = match package.tcx.documentation_string_of(field.id) {
-        Some(str) => format!("\n\n{str}"),
-        None => String::new(),
-    };
+    let documentation = documentation_of(state, location, field.id, Some(callee_type.instance_of));
 
     Ok(format!(
         "```lm\n{} {}: {field_type};\n```{documentation}",
@@ -114,9 +348,16 @@ pub(crate) fn hover_content_of_member(
 }
 
 pub(crate) fn hover_content_of_variant(state: &State, location: Location, name: &lume_hir::Path) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
+
+    // Variant paths always name the enum before the case, but patterns may be
+    // incomplete while being edited.
+    let Some(enum_name) = name.clone().parent() else {
+        return Ok(String::new());
+    };
 
-    let enum_name = name.clone().parent().unwrap();
     let enum_def = package.tcx.enum_def_of_name(&enum_name)?;
     let enum_case = package.tcx.enum_case_with_name(name)?;
 
@@ -133,10 +374,7 @@ pub(crate) fn hover_content_of_variant(state: &State, location: Location, name:
         format!("({fields})")
     };
 
-    let documentation = match package.tcx.documentation_string_of(enum_def.id) {
-        Some(str) => format!("\n\n{str}"),
-        None => String::new(),
-    };
+    let documentation = documentation_of(state, location, enum_case.id, Some(enum_def.id));
 
     Ok(format!(
         "```lm\n{:+}::{}{fields}\n```{documentation}",
@@ -145,7 +383,9 @@ pub(crate) fn hover_content_of_variant(state: &State, location: Location, name:
 }
 
 pub(crate) fn hover_content_of_pattern(state: &State, location: Location, id: NodeId) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
 
     let Some(lume_hir::Node::Pattern(pattern)) = package.tcx.hir_node(id) else {
         return Ok(String::new());
@@ -154,16 +394,21 @@ pub(crate) fn hover_content_of_pattern(state: &State, location: Location, id: No
     let pattern_ty = package.tcx.type_of_pattern(pattern)?;
     let pattern_ty_name = package.tcx.new_named_type(&pattern_ty, true)?;
 
-    let documentation = match package.tcx.documentation_string_of(pattern_ty.instance_of) {
-        Some(str) => format!("\n\n{str}"),
-        None => String::new(),
-    };
+    let documentation = documentation_of(state, location, pattern_ty.instance_of, None);
+
+    // Bindings, such as the fields destructured from an enum case, show the name
+    // they bind along with the type of the bound value.
+    if let lume_hir::PatternKind::Identifier(ident) = &pattern.kind {
+        return Ok(format!("```lm\n{}: {pattern_ty_name}\n```{documentation}", ident.name));
+    }
 
     Ok(format!("```lm\n{pattern_ty_name}\n```{documentation}"))
 }
 
 pub(crate) fn hover_content_of_field(state: &State, location: Location, id: NodeId) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
 
     let Some(lume_hir::Node::Field(field)) = package.tcx.hir_node(id) else {
         return Ok(String::new());
@@ -173,10 +418,7 @@ pub(crate) fn hover_content_of_field(state: &State, location: Location, id: Node
     let field_type_ref = package.tcx.mk_type_ref_from(&field.field_type, struct_def.id)?;
     let field_type = package.tcx.new_named_type(&field_type_ref, true)?;
 
-    let documentation = match package.tcx.documentation_string_of(id) {
-        Some(str) => format!("\n\n{str}"),
-        None => String::new(),
-    };
+    let documentation = documentation_of(state, location, id, Some(struct_def.id));
 
     Ok(format!(
         "```lm\n{:+}\n\n{}: {field_type};\n```{documentation}",
@@ -185,18 +427,29 @@ pub(crate) fn hover_content_of_field(state: &State, location: Location, id: Node
 }
 
 pub(crate) fn hover_content_of_literal(state: &State, location: Location, id: NodeId) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
-    let literal_type = package.tcx.type_of(id)?;
-
-    let Some(literal_type_def) = package.tcx.tdb().type_(literal_type.instance_of) else {
+    let Some(package) = state.package_of(location.file.package) else {
         return Ok(String::new());
     };
+    let literal_type = package.tcx.type_of(id)?;
+
+    // Render the inferred type itself, rather than the declaration of the type,
+    // since the type of numeric literals is often inferred from their context.
+    let literal_type_name = package.tcx.new_named_type(&literal_type, true)?;
+    let documentation = documentation_of(state, location, literal_type.instance_of, None);
 
-    hover_content_of_type(state, location, &literal_type_def.name)
+    Ok(format!("```lm\n{literal_type_name}\n```{documentation}"))
 }
 
 pub(crate) fn hover_content_of_call(state: &State, location: Location, id: NodeId) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
+    // Intrinsics have no declaration which could be resolved as a callable, so
+    // their signature is built from the types of their operands instead.
+    if let Some(lume_hir::ExpressionKind::IntrinsicCall(intrinsic)) = package.tcx.hir_expr(id).map(|e| &e.kind) {
+        return hover_content_of_intrinsic(state, location, id, intrinsic);
+    }
+
     let Some(expr) = package.tcx.hir_call_expr(id) else {
         return Ok(String::new());
     };
@@ -206,8 +459,49 @@ pub(crate) fn hover_content_of_call(state: &State, location: Location, id: NodeI
     hover_content_of_callable(state, location, callable.to_call_reference())
 }
 
+/// Renders the signature of the given intrinsic call, from the name of the
+/// intrinsic, the types of its operands and the type of its result.
+fn hover_content_of_intrinsic(
+    state: &State,
+    location: Location,
+    id: NodeId,
+    intrinsic: &lume_hir::IntrinsicCall,
+) -> Result<String> {
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
+
+    let operands = intrinsic
+        .kind
+        .arguments()
+        .iter()
+        .map(|argument| {
+            let operand_type = package.tcx.type_of(*argument)?;
+
+            Ok(package.tcx.new_named_type(&operand_type, true)?.to_string())
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    let result_type = package.tcx.type_of(id)?;
+    let result_type_name = package.tcx.new_named_type(&result_type, true)?;
+
+    Ok(intrinsic_signature(
+        intrinsic.kind.name(),
+        &operands,
+        &result_type_name.to_string(),
+    ))
+}
+
+/// Renders the signature of an intrinsic with the given name, operand types
+/// and result type.
+fn intrinsic_signature(name: &str, operands: &[String], result_type: &str) -> String {
+    format!("```lm\nintrinsic {name}({}) -> {result_type}\n```", operands.join(", "))
+}
+
 pub(crate) fn hover_content_of_variable_ref(state: &State, location: Location, id: NodeId) -> Result<String> {
-    let package = state.checked.graph.packages.get(&location.file.package).unwrap();
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
 
     let Some(lume_hir::ExpressionKind::Variable(variable_ref)) = package.tcx.hir_expr(id).map(|e| &e.kind) else {
         return Ok(String::new());
@@ -224,3 +518,173 @@ pub(crate) fn hover_content_of_variable_ref(state: &State, location: Location, i
 
     Ok(format!("```lm\nlet {variable_name}: {variable_type_name};\n```"))
 }
+
+/// Renders the documentation comment of the node with the given ID as a
+/// Markdown section, to be appended below the code block of a hover.
+///
+/// If the node has no documentation, the documentation of the given owner is
+/// used instead, such as the type which declares a field or enum case. If
+/// neither has documentation, the section is omitted entirely.
+pub(crate) fn documentation_of(state: &State, location: Location, id: NodeId, owner: Option<NodeId>) -> String {
+    match documentation_text_of(state, location, id, owner) {
+        Some(str) => format!("\n\n{str}"),
+        None => String::new(),
+    }
+}
+
+/// Gets the trimmed documentation comment of the node with the given ID,
+/// falling back to the documentation of the given owner.
+///
+/// Returns [`None`] if neither node has any documentation.
+pub(crate) fn documentation_text_of(
+    state: &State,
+    location: Location,
+    id: NodeId,
+    owner: Option<NodeId>,
+) -> Option<String> {
+    let documentation_string_of = |id: NodeId| {
+        let package_id = declaring_package_of(state, id, location.file.package);
+        let package = state.package_of(package_id)?;
+
+        package.tcx.documentation_string_of(id)
+    };
+
+    let documentation = documentation_string_of(id).or_else(|| owner.and_then(documentation_string_of))?;
+
+    let documentation = documentation.trim();
+    if documentation.is_empty() {
+        return None;
+    }
+
+    Some(documentation.to_owned())
+}
+
+/// Gets the ID of the package which declares the node with the given ID.
+///
+/// The HIR of a package only contains its own nodes, so symbols which are
+/// referenced from a dependent package must be read from the package which
+/// declares them. If no package declares the node, the given fallback package
+/// is returned instead.
+fn declaring_package_of(state: &State, id: NodeId, fallback: PackageId) -> PackageId {
+    if let Some(package) = state.checked.graph.packages.get(&fallback)
+        && package.tcx.hir_node(id).is_some()
+    {
+        return fallback;
+    }
+
+    state
+        .checked
+        .graph
+        .packages
+        .iter()
+        .find(|(_, package)| package.tcx.hir_node(id).is_some())
+        .map_or(fallback, |(package_id, _)| *package_id)
+}
+
+/// Renders the signature of the given callable, using the fully-qualified name
+/// of the callable.
+pub(crate) fn signature_of_callable(state: &State, location: Location, reference: CallReference) -> Result<String> {
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(String::new());
+    };
+    let callable = package.tcx.callable_of(reference)?;
+
+    let identifier = lume_hir::Identifier {
+        name: format!("{:+}", callable.name()),
+        location: callable.name().location,
+    };
+
+    package.tcx.sig_to_string(&identifier, callable.signature(), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::tests::{checked_test_state, location_of_text, test_arcfile};
+
+    fn hover_text(state: &State, uri: &lsp_types::Uri, needle: &str) -> String {
+        let location = location_of_text(state, uri, needle);
+
+        hover_content_of(state, location).unwrap().unwrap().0
+    }
+
+    const SHAPES: &str = "enum Shape {
+    Circle(Float),
+    Rectangle(Float, Float),
+}
+
+fn area(shape: Shape) -> Float {
+    switch shape {
+        Shape::Circle(radius) => radius * radius,
+        Shape::Rectangle(width, height) => width * height,
+    }
+}
+";
+
+    #[test]
+    fn intrinsic_signature_of_arithmetic() {
+        let operands = [String::from("Int32"), String::from("Int32")];
+
+        assert_eq!(
+            intrinsic_signature("int_add", &operands, "Int32"),
+            "```lm\nintrinsic int_add(Int32, Int32) -> Int32\n```"
+        );
+    }
+
+    #[test]
+    fn intrinsic_signature_without_operands() {
+        assert_eq!(
+            intrinsic_signature("metadata", &[], "Type"),
+            "```lm\nintrinsic metadata() -> Type\n```"
+        );
+    }
+
+    #[test]
+    fn hover_variant_pattern_binding() {
+        let (state, _messages, workspace) = checked_test_state(&[""], &[
+            ("Arcfile", &test_arcfile("shapes", "")),
+            ("src/main.lm", SHAPES),
+        ]);
+        let uri = workspace.uri_of("src/main.lm");
+
+        assert!(hover_text(&state, &uri, "radius)").starts_with("```lm\nradius: Float\n```"));
+        assert!(hover_text(&state, &uri, "height)").starts_with("```lm\nheight: Float\n```"));
+    }
+
+    #[test]
+    fn hover_variant_pattern_name() {
+        let (state, _messages, workspace) = checked_test_state(&[""], &[
+            ("Arcfile", &test_arcfile("shapes", "")),
+            ("src/main.lm", SHAPES),
+        ]);
+        let uri = workspace.uri_of("src/main.lm");
+
+        assert!(hover_text(&state, &uri, "Circle(radius)").contains("Shape::Circle(Float)"));
+        assert!(hover_text(&state, &uri, "Rectangle(width").contains("Shape::Rectangle(Float, Float)"));
+    }
+
+    #[test]
+    fn hover_type_from_dependency() {
+        let (state, _messages, workspace) = checked_test_state(&["app"], &[
+            ("geometry/Arcfile", &test_arcfile("geometry", "")),
+            (
+                "geometry/src/lib.lm",
+                "/// A point on a two-dimensional plane.\npub struct Point {\n    pub x: Int32;\n    pub y: Int32;\n}\n",
+            ),
+            (
+                "app/Arcfile",
+                &test_arcfile("app", "\"geometry\": { path: \"../geometry\" }"),
+            ),
+            (
+                "app/src/main.lm",
+                "import geometry (Point)\n\nfn origin() -> Point {\n    return Point { x: 0, y: 0 };\n}\n",
+            ),
+        ]);
+        let uri = workspace.uri_of("app/src/main.lm");
+
+        let content = hover_text(&state, &uri, "Point {\n    return");
+
+        assert!(content.contains("pub struct Point"), "{content}");
+        assert!(content.contains("A point on a two-dimensional plane."), "{content}");
+    }
+}
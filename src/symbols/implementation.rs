@@ -0,0 +1,103 @@
+use lume_errors::Result;
+use lume_hir::WithLocation as _;
+use lume_infer::query::CallReference;
+use lume_span::{Location, NodeId};
+
+use crate::state::State;
+use crate::symbols::lookup::SymbolKind;
+
+/// Finds the implementations of the trait or trait method at the given
+/// location.
+///
+/// For traits, the location of each implementation block is returned, whereas
+/// for trait methods, the location of the method within each implementation is
+/// returned. Any other symbol has no implementations.
+pub(crate) fn implementations_of(state: &State, location: Location) -> Result<Vec<Location>> {
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        log::warn!("could not find matching node for {location}");
+        return Ok(Vec::new());
+    };
+
+    let Some(package) = state.checked.graph.packages.get(&sym.location.file.package) else {
+        return Ok(Vec::new());
+    };
+
+    let reference = match &sym.kind {
+        SymbolKind::Type { name } => {
+            let Some(trait_id) = package.tcx.tdb().find_type(name).map(|ty| ty.id) else {
+                return Ok(Vec::new());
+            };
+
+            return Ok(trait_impls_of(state, trait_id).map(|node| node.location()).collect());
+        }
+        SymbolKind::Callable { reference } => *reference,
+        SymbolKind::Call { id } => {
+            let Some(expr) = package.tcx.hir_call_expr(*id) else {
+                return Ok(Vec::new());
+            };
+
+            package.tcx.probe_callable(expr)?.to_call_reference()
+        }
+        _ => return Ok(Vec::new()),
+    };
+
+    let CallReference::Method(method_id) = reference else {
+        return Ok(Vec::new());
+    };
+
+    let Some((trait_id, method_name)) = trait_method_of(state, method_id) else {
+        return Ok(Vec::new());
+    };
+
+    let mut locations = Vec::new();
+
+    for node in trait_impls_of(state, trait_id) {
+        let lume_hir::Node::TraitImpl(trait_impl) = node else {
+            continue;
+        };
+
+        locations.extend(
+            trait_impl
+                .methods
+                .iter()
+                .filter(|method| method.name.name == method_name)
+                .map(|method| method.name.location),
+        );
+    }
+
+    Ok(locations)
+}
+
+/// Iterates over all trait implementations within the workspace, which
+/// implement the trait with the given ID.
+fn trait_impls_of(state: &State, trait_id: NodeId) -> impl Iterator<Item = &lume_hir::Node> {
+    // Implementations can be declared in any package, so all packages within the
+    // workspace must be searched.
+    state.checked.graph.packages.values().flat_map(move |package| {
+        let tdb = package.tcx.tdb();
+
+        package.tcx.hir().nodes().values().filter(move |node| {
+            matches!(node, lume_hir::Node::TraitImpl(trait_impl)
+                if tdb.find_type(&trait_impl.name.name).map(|ty| ty.id) == Some(trait_id))
+        })
+    })
+}
+
+/// Finds the trait which declares the method with the given ID, along with the
+/// name of the method.
+///
+/// If the method isn't declared within a trait, returns [`None`].
+fn trait_method_of(state: &State, method_id: NodeId) -> Option<(NodeId, String)> {
+    state.checked.graph.packages.values().find_map(|package| {
+        package.tcx.hir().nodes().values().find_map(|node| {
+            let lume_hir::Node::Type(lume_hir::TypeDefinition::Trait(trait_def)) = node else {
+                return None;
+            };
+
+            let method = trait_def.methods.iter().find(|method| method.id == method_id)?;
+            let trait_id = package.tcx.tdb().find_type(&trait_def.name)?.id;
+
+            Some((trait_id, method.name.name.clone()))
+        })
+    })
+}
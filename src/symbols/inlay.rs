@@ -0,0 +1,93 @@
+use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, Range};
+use lume_errors::Result;
+use lume_span::{Location, SourceFile};
+
+use crate::position::{index_from_position, position_from_index};
+use crate::state::State;
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Gets the inferred types of all variable declarations within the given
+/// range, which do not have an explicitly declared type.
+///
+/// If inlay hints are disabled within the configuration, no hints are returned.
+pub(crate) fn inlay_hints_of(state: &State, file: &SourceFile, range: Range) -> Result<Vec<InlayHint>> {
+    if !state.config.enable_inlay_hints {
+        return Ok(Vec::new());
+    }
+
+    let Some(package) = state.checked.graph.packages.get(&file.package) else {
+        return Ok(Vec::new());
+    };
+
+    let content = file.content.as_str();
+    let encoding = state.position_encoding;
+
+    let range_start = index_from_position(
+        content,
+        range.start.line as usize,
+        range.start.character as usize,
+        encoding,
+    );
+
+    let range_end = index_from_position(content, range.end.line as usize, range.end.character as usize, encoding);
+
+    let mut visitor = VariableVisitor {
+        file,
+        decls: Vec::new(),
+    };
+    traverse(package.tcx.hir(), &mut visitor)?;
+
+    let mut hints = Vec::new();
+
+    for (stmt_location, decl) in visitor.decls {
+        if stmt_location.index.end < range_start || stmt_location.index.start > range_end {
+            continue;
+        }
+
+        let type_name = match package
+            .tcx
+            .type_of_vardecl(&decl)
+            .and_then(|ty| package.tcx.new_named_type(&ty, true))
+        {
+            Ok(type_name) => type_name,
+            Err(err) => {
+                log::debug!("could not resolve type of `{}`: {}", decl.name, err.message());
+                continue;
+            }
+        };
+
+        hints.push(InlayHint {
+            position: position_from_index(content, decl.name.location.index.end, encoding),
+            label: InlayHintLabel::String(format!(": {type_name}")),
+            kind: Some(InlayHintKind::TYPE),
+            text_edits: None,
+            tooltip: None,
+            padding_left: None,
+            padding_right: None,
+            data: None,
+        });
+    }
+
+    Ok(hints)
+}
+
+/// Collects all variable declarations within a single file, which lack an
+/// explicitly declared type.
+struct VariableVisitor<'a> {
+    file: &'a SourceFile,
+    decls: Vec<(Location, lume_hir::VariableDeclaration)>,
+}
+
+impl Visitor for VariableVisitor<'_> {
+    fn visit_stmt(&mut self, stmt: &lume_hir::Statement) -> Result<()> {
+        let lume_hir::StatementKind::Variable(decl) = &stmt.kind else {
+            return Ok(());
+        };
+
+        if decl.declared_type.is_none() && stmt.location.file.id == self.file.id {
+            self.decls.push((stmt.location, decl.clone()));
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,15 @@
-use indexmap::IndexSet;
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use lume_driver::CheckedPackageGraph;
 use lume_errors::Result;
 use lume_hir::WithLocation as _;
 use lume_infer::query::CallReference;
-use lume_span::{Location, NodeId};
+use lume_span::{Location, NodeId, PackageId, SourceFile, SourceFileId};
 
-use crate::symbols::visitor::{Visitor, traverse};
+use crate::symbols::visitor::{Visitor, traverse, traverse_files};
 
 #[derive(Hash, Debug, Clone, PartialEq, Eq)]
 pub(crate) struct SymbolEntry {
@@ -65,11 +70,51 @@ pub(crate) enum SymbolKind {
 
     /// Symbol refers to a variable reference.
     VariableReference { id: NodeId },
+
+    /// Symbol refers to the declaration of a type parameter.
+    TypeParameter { name: lume_hir::Identifier },
+}
+
+impl SymbolKind {
+    /// Gets the precedence of the symbol kind, when multiple symbols share the
+    /// exact same location. Higher values are more specific and are preferred:
+    ///
+    /// 1. [`SymbolKind::Type`] is the least specific, since every path segment
+    ///    which names a type is recorded as a type as well;
+    /// 2. [`SymbolKind::Pattern`] covers any pattern, regardless of its use;
+    /// 3. symbols which refer to a single node, such as calls, fields and
+    ///    variable references;
+    /// 4. [`SymbolKind::Variant`] is the most specific, since the path of an
+    ///    enum case also names its enum type.
+    fn precedence(&self) -> u8 {
+        match self {
+            SymbolKind::Type { .. } => 0,
+            SymbolKind::Pattern { .. } => 1,
+            SymbolKind::Callable { .. }
+            | SymbolKind::Field { .. }
+            | SymbolKind::Call { .. }
+            | SymbolKind::Literal { .. }
+            | SymbolKind::Member { .. }
+            | SymbolKind::VariableReference { .. }
+            | SymbolKind::TypeParameter { .. } => 2,
+            SymbolKind::Variant { .. } => 3,
+        }
+    }
 }
 
 #[derive(Default)]
 pub(crate) struct SymbolLookup {
-    symbols: IndexSet<SymbolEntry>,
+    /// All symbols within the package, grouped by their file.
+    files: IndexMap<SourceFileId, FileSymbols>,
+}
+
+/// Symbols within a single file, sorted by their start index.
+struct FileSymbols {
+    symbols: Vec<SymbolEntry>,
+
+    /// Length of the longest span of any symbol within the file, which bounds
+    /// how far back a lookup has to search for a containing symbol.
+    max_len: usize,
 }
 
 impl SymbolLookup {
@@ -77,38 +122,353 @@ impl SymbolLookup {
         let mut visitor = LocationVisitor::default();
         traverse(hir, &mut visitor)?;
 
-        Ok(Self {
-            symbols: visitor.symbols,
-        })
+        let mut lookup = Self::default();
+        lookup.insert_symbols(visitor);
+
+        Ok(lookup)
+    }
+
+    /// Rebuilds the symbols of the given files from the HIR, while keeping the
+    /// symbols of all other files as-is.
+    pub fn update_files(&mut self, hir: &lume_hir::Map, files: &HashSet<SourceFileId>) -> Result<()> {
+        let mut visitor = LocationVisitor::default();
+        traverse_files(hir, files, &mut visitor)?;
+
+        self.remove_files(files.iter());
+        self.insert_symbols(visitor);
+
+        Ok(())
+    }
+
+    /// Removes all symbols within the given files.
+    pub fn remove_files<'a>(&mut self, files: impl Iterator<Item = &'a SourceFileId>) {
+        for file in files {
+            self.files.swap_remove(file);
+        }
+    }
+
+    fn insert_symbols(&mut self, visitor: LocationVisitor) {
+        let mut files: IndexMap<SourceFileId, Vec<SymbolEntry>> = IndexMap::new();
+
+        for sym in visitor.symbols.into_values() {
+            files.entry(sym.location.file.id).or_default().push(sym);
+        }
+
+        for (file, mut symbols) in files {
+            symbols.sort();
+
+            let max_len = symbols
+                .iter()
+                .map(|sym| sym.location.index.len())
+                .max()
+                .unwrap_or_default();
+
+            self.files.insert(file, FileSymbols { symbols, max_len });
+        }
+    }
+
+    /// Iterates over all symbols within the lookup.
+    pub fn iter(&self) -> impl Iterator<Item = &SymbolEntry> {
+        self.files.values().flat_map(|file| file.symbols.iter())
     }
 
-    pub fn extend(&mut self, other: SymbolLookup) {
-        self.symbols.extend(other.symbols);
+    /// Iterates over all symbols within the file with the given ID.
+    pub fn symbols_in_file(&self, file: SourceFileId) -> impl Iterator<Item = &SymbolEntry> {
+        self.files.get(&file).into_iter().flat_map(|file| file.symbols.iter())
     }
 
+    /// Finds the smallest symbol which contains the given location.
     pub fn lookup_position(&self, location: Location) -> Option<&SymbolEntry> {
+        let FileSymbols { symbols, max_len } = self.files.get(&location.file.id)?;
         let idx = location.index.start;
 
-        let symbols_within_range = self.symbols.iter().filter(|sym| {
-            sym.location.file.id == location.file.id && sym.location.start() <= idx && sym.location.end() >= idx
+        // Since symbols are sorted, all symbols which start at or before the
+        // location directly precede this point.
+        let end = symbols.partition_point(|sym| sym.location.start() <= idx);
+
+        let mut smallest: Option<&SymbolEntry> = None;
+
+        for sym in symbols[..end].iter().rev() {
+            // No symbol which starts further away than the longest span can contain the
+            // location, so misses don't have to scan the rest of the file.
+            if idx - sym.location.start() > *max_len {
+                break;
+            }
+
+            // Synthesized nodes may have zero-length spans, which would otherwise always
+            // be the smallest symbol and hide the real symbol next to them.
+            if sym.location.index.is_empty() {
+                continue;
+            }
+
+            // Any symbol which starts further away than the length of the smallest
+            // symbol so far cannot be any smaller.
+            if let Some(smallest) = smallest
+                && idx - sym.location.start() > smallest.location.index.len()
+            {
+                break;
+            }
+
+            if sym.location.end() >= idx
+                && smallest.is_none_or(|smallest| sym.location.index.len() <= smallest.location.index.len())
+            {
+                smallest = Some(sym);
+            }
+        }
+
+        smallest
+    }
+}
+
+/// Symbol lookups for all packages within the workspace.
+///
+/// Lookups are cached per-package and are only rebuilt when the source files of
+/// the package have changed since the last update.
+#[derive(Default)]
+pub(crate) struct WorkspaceSymbols {
+    packages: IndexMap<PackageId, PackageSymbols>,
+}
+
+struct PackageSymbols {
+    source_hash: u64,
+
+    /// Hashes of each file within the package, covering both the content of
+    /// the file and the IDs of the nodes declared within it.
+    file_hashes: IndexMap<SourceFileId, u64>,
+
+    lookup: SymbolLookup,
+}
+
+impl WorkspaceSymbols {
+    /// Updates the symbol lookups from the given package graph, reusing the
+    /// existing lookups of all unchanged packages.
+    ///
+    /// Within changed packages, only the symbols of changed files are rebuilt.
+    /// The lookups of changed packages are built in parallel, since they are
+    /// independent of each other.
+    pub fn update(&mut self, graph: &CheckedPackageGraph) {
+        let mut packages = IndexMap::with_capacity(graph.packages.len());
+        let mut changed = Vec::new();
+
+        for (id, package) in &graph.packages {
+            let source_hash = hash_sources(package.sources.iter());
+
+            match self.packages.swap_remove(id) {
+                Some(cached) if cached.source_hash == source_hash => {
+                    packages.insert(*id, Some(cached));
+                }
+                cached => {
+                    // Reserve the position of the package, so the order of the lookups
+                    // matches the graph, no matter which thread finishes first.
+                    packages.insert(*id, None);
+                    changed.push((*id, package, source_hash, cached));
+                }
+            }
+        }
+
+        let threads = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+        let chunk_size = changed.len().div_ceil(threads).max(1);
+
+        let built = std::thread::scope(|scope| {
+            let handles = changed
+                .chunks_mut(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter_mut()
+                            .map(|(id, package, source_hash, cached)| {
+                                let symbols = build_package_symbols(
+                                    package.tcx.hir(),
+                                    package.sources.iter(),
+                                    *source_hash,
+                                    cached.take(),
+                                )
+                                .map_err(|err| {
+                                    format!(
+                                        "error while updating symbol graph for package {}: {}",
+                                        package.package,
+                                        err.message()
+                                    )
+                                });
+
+                                (*id, symbols)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| match handle.join() {
+                    Ok(lookups) => lookups,
+                    Err(_) => {
+                        log::error!("thread panicked while updating symbol graph");
+                        Vec::new()
+                    }
+                })
+                .collect::<Vec<_>>()
         });
 
-        if let Some(sym) = symbols_within_range.min_by_key(|sym| sym.location.index.len()) {
-            return Some(sym);
+        for (id, symbols) in built {
+            match symbols {
+                Ok(symbols) => {
+                    packages.insert(id, Some(symbols));
+                }
+                Err(message) => log::error!("{message}"),
+            }
         }
 
-        None
+        self.packages = packages
+            .into_iter()
+            .filter_map(|(id, package)| Some((id, package?)))
+            .collect();
+    }
+
+    /// Iterates over all symbols within all packages.
+    pub fn iter(&self) -> impl Iterator<Item = &SymbolEntry> {
+        self.packages.values().flat_map(|package| package.lookup.iter())
+    }
+
+    /// Iterates over all symbols which are located within the given file.
+    pub fn symbols_in_file<'a>(&'a self, file: &'a SourceFile) -> impl Iterator<Item = &'a SymbolEntry> {
+        self.packages
+            .get(&file.package)
+            .into_iter()
+            .flat_map(|package| package.lookup.symbols_in_file(file.id))
+    }
+
+    /// Finds the smallest symbol which contains the given location, using the
+    /// lookup of the package which owns the location.
+    pub fn lookup_position(&self, location: Location) -> Option<&SymbolEntry> {
+        self.packages
+            .get(&location.file.package)?
+            .lookup
+            .lookup_position(location)
     }
 }
 
+/// Builds the symbols of a changed package from its HIR.
+///
+/// If the package was indexed before, only the files whose hash has changed
+/// are traversed again. Since node IDs are included within the hash of each
+/// file, files whose nodes were renumbered by edits in other files are rebuilt
+/// as well.
+fn build_package_symbols<'a>(
+    hir: &lume_hir::Map,
+    sources: impl Iterator<Item = &'a Arc<SourceFile>>,
+    source_hash: u64,
+    cached: Option<PackageSymbols>,
+) -> Result<PackageSymbols> {
+    let file_hashes = hash_files(hir, sources);
+
+    let Some(PackageSymbols {
+        file_hashes: cached_hashes,
+        mut lookup,
+        ..
+    }) = cached
+    else {
+        return Ok(PackageSymbols {
+            source_hash,
+            file_hashes,
+            lookup: SymbolLookup::from_hir(hir)?,
+        });
+    };
+
+    let changed_files = file_hashes
+        .iter()
+        .filter(|(file, hash)| cached_hashes.get(*file) != Some(*hash))
+        .map(|(file, _)| *file)
+        .collect::<HashSet<_>>();
+
+    lookup.remove_files(cached_hashes.keys().filter(|file| !file_hashes.contains_key(*file)));
+    lookup.update_files(hir, &changed_files)?;
+
+    Ok(PackageSymbols {
+        source_hash,
+        file_hashes,
+        lookup,
+    })
+}
+
+/// Hashes each of the given files, including the content of the file as well
+/// as the location and ID of each node declared within it.
+///
+/// Since the iteration order of the nodes is not guaranteed, the hashes of the
+/// nodes are combined in an order-independent manner.
+fn hash_files<'a>(
+    hir: &lume_hir::Map,
+    sources: impl Iterator<Item = &'a Arc<SourceFile>>,
+) -> IndexMap<SourceFileId, u64> {
+    let mut file_hashes: IndexMap<SourceFileId, u64> = IndexMap::new();
+
+    for source in sources {
+        let mut hasher = DefaultHasher::new();
+        source.content.hash(&mut hasher);
+
+        file_hashes.insert(source.id, hasher.finish());
+    }
+
+    for (id, node) in hir.nodes() {
+        let location = node.location();
+
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        location.hash(&mut hasher);
+
+        let file_hash = file_hashes.entry(location.file.id).or_default();
+        *file_hash = file_hash.wrapping_add(hasher.finish());
+    }
+
+    file_hashes
+}
+
+fn hash_sources<'a>(sources: impl Iterator<Item = &'a Arc<SourceFile>>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for source in sources {
+        source.name.to_pathbuf().hash(&mut hasher);
+        source.content.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+/// Collects a single symbol for each distinct location within the HIR.
+///
+/// When multiple symbols share the exact same location, only the symbol with
+/// the highest [`SymbolKind::precedence`] is kept, so lookups never have to
+/// choose between symbols of equal size.
 #[derive(Default)]
 struct LocationVisitor {
-    symbols: IndexSet<SymbolEntry>,
+    symbols: IndexMap<Location, SymbolEntry>,
+}
+
+impl LocationVisitor {
+    fn insert(&mut self, entry: SymbolEntry) {
+        match self.symbols.get(&entry.location) {
+            Some(existing) if existing.kind.precedence() >= entry.kind.precedence() => {}
+            _ => {
+                self.symbols.insert(entry.location, entry);
+            }
+        }
+    }
+
+    fn insert_type_parameters<'a>(&mut self, type_parameters: impl Iterator<Item = &'a lume_hir::TypeParameter>) {
+        for type_param in type_parameters {
+            self.insert(SymbolEntry {
+                kind: SymbolKind::TypeParameter {
+                    name: type_param.name.clone(),
+                },
+                location: type_param.name.location,
+            });
+        }
+    }
 }
 
 impl Visitor for LocationVisitor {
     fn visit_type(&mut self, ty: &lume_hir::Type) -> Result<()> {
-        self.symbols.insert_sorted(SymbolEntry {
+        self.insert(SymbolEntry {
             kind: SymbolKind::Type { name: ty.name.clone() },
             location: ty.location,
         });
@@ -119,69 +479,89 @@ impl Visitor for LocationVisitor {
     fn visit_node(&mut self, node: &lume_hir::Node) -> Result<()> {
         match node {
             lume_hir::Node::Function(func) => {
-                self.symbols.insert_sorted(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Callable {
                         reference: CallReference::Function(func.id),
                     },
                     location: func.name.location,
                 });
+
+                self.insert_type_parameters(func.type_parameters.iter());
             }
             lume_hir::Node::Method(method) => {
-                self.symbols.insert_sorted(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Callable {
                         reference: CallReference::Method(method.id),
                     },
                     location: method.name.location,
                 });
+
+                self.insert_type_parameters(method.type_parameters.iter());
             }
             lume_hir::Node::TraitMethodDef(method) => {
-                self.symbols.insert_sorted(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Callable {
                         reference: CallReference::Method(method.id),
                     },
                     location: method.name.location,
                 });
+
+                self.insert_type_parameters(method.type_parameters.iter());
             }
             lume_hir::Node::TraitMethodImpl(method) => {
-                self.symbols.insert_sorted(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Callable {
                         reference: CallReference::Method(method.id),
                     },
                     location: method.name.location,
                 });
+
+                self.insert_type_parameters(method.type_parameters.iter());
             }
             lume_hir::Node::Type(type_def) => match type_def {
                 lume_hir::TypeDefinition::Struct(struct_def) => {
-                    self.symbols.insert_sorted(SymbolEntry {
+                    self.insert(SymbolEntry {
                         kind: SymbolKind::Type {
                             name: struct_def.name.clone(),
                         },
                         location: struct_def.name().location(),
                     });
+
+                    self.insert_type_parameters(struct_def.type_parameters.iter());
                 }
                 lume_hir::TypeDefinition::Trait(trait_def) => {
-                    self.symbols.insert_sorted(SymbolEntry {
+                    self.insert(SymbolEntry {
                         kind: SymbolKind::Type {
                             name: trait_def.name.clone(),
                         },
                         location: trait_def.name().location(),
                     });
+
+                    self.insert_type_parameters(trait_def.type_parameters.iter());
                 }
                 lume_hir::TypeDefinition::Enum(enum_def) => {
-                    self.symbols.insert_sorted(SymbolEntry {
+                    self.insert(SymbolEntry {
                         kind: SymbolKind::Type {
                             name: enum_def.name.clone(),
                         },
                         location: enum_def.name().location(),
                     });
+
+                    self.insert_type_parameters(enum_def.type_parameters.iter());
                 }
             },
             lume_hir::Node::Field(field) => {
-                self.symbols.insert_sorted(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Field { id: field.id },
                     location: field.name.location,
                 });
             }
+            lume_hir::Node::Impl(type_impl) => {
+                self.insert_type_parameters(type_impl.type_parameters.iter());
+            }
+            lume_hir::Node::TraitImpl(trait_impl) => {
+                self.insert_type_parameters(trait_impl.type_parameters.iter());
+            }
             _ => {}
         }
 
@@ -193,7 +573,7 @@ impl Visitor for LocationVisitor {
             lume_hir::ExpressionKind::Assignment(_) => {}
             lume_hir::ExpressionKind::Cast(_) => {}
             lume_hir::ExpressionKind::Construct(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Type {
                         name: expr.path.clone(),
                     },
@@ -201,19 +581,19 @@ impl Visitor for LocationVisitor {
                 });
             }
             lume_hir::ExpressionKind::StaticCall(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Call { id: expr.id },
                     location: expr.name.name().location,
                 });
             }
             lume_hir::ExpressionKind::InstanceCall(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Call { id: expr.id },
                     location: expr.name.location(),
                 });
             }
             lume_hir::ExpressionKind::IntrinsicCall(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Call { id: expr.id },
                     location: expr.location(),
                 });
@@ -221,7 +601,7 @@ impl Visitor for LocationVisitor {
             lume_hir::ExpressionKind::If(_) => {}
             lume_hir::ExpressionKind::Is(_) => {}
             lume_hir::ExpressionKind::Member(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     location: expr.name.location,
                     kind: SymbolKind::Member {
                         callee: expr.callee,
@@ -232,7 +612,7 @@ impl Visitor for LocationVisitor {
             lume_hir::ExpressionKind::Scope(_) => {}
             lume_hir::ExpressionKind::Switch(_) => {}
             lume_hir::ExpressionKind::Variant(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     location: expr.name.location(),
                     kind: SymbolKind::Variant {
                         name: expr.name.clone(),
@@ -240,13 +620,13 @@ impl Visitor for LocationVisitor {
                 });
             }
             lume_hir::ExpressionKind::Variable(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::VariableReference { id: expr.id },
                     location: expr.location,
                 });
             }
             lume_hir::ExpressionKind::Literal(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Literal { id: expr.id },
                     location: expr.location,
                 });
@@ -261,7 +641,7 @@ impl Visitor for LocationVisitor {
 
         while let Some(parent) = current {
             if let lume_hir::PathSegment::Type { location, .. } = &parent.name {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     kind: SymbolKind::Type { name: parent.clone() },
                     location: *location,
                 });
@@ -276,7 +656,7 @@ impl Visitor for LocationVisitor {
     fn visit_pattern(&mut self, pattern: &lume_hir::Pattern) -> Result<()> {
         match &pattern.kind {
             lume_hir::PatternKind::Variant(expr) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     location: expr.name.location(),
                     kind: SymbolKind::Variant {
                         name: expr.name.clone(),
@@ -286,7 +666,7 @@ impl Visitor for LocationVisitor {
             lume_hir::PatternKind::Identifier(_)
             | lume_hir::PatternKind::Literal(_)
             | lume_hir::PatternKind::Wildcard(_) => {
-                self.symbols.insert(SymbolEntry {
+                self.insert(SymbolEntry {
                     location: pattern.location,
                     kind: SymbolKind::Pattern { id: pattern.id },
                 });
@@ -296,3 +676,414 @@ impl Visitor for LocationVisitor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lume_span::{FileName, Internable as _};
+
+    use super::*;
+    use crate::state::tests::{checked_test_state, replace_test_document, test_arcfile};
+
+    fn source_file(content: &str) -> Arc<SourceFile> {
+        Arc::new(SourceFile::new(
+            PackageId::empty(),
+            FileName::Real(std::path::PathBuf::from("main.lm")),
+            content.to_owned(),
+        ))
+    }
+
+    fn location(file: &Arc<SourceFile>, index: std::ops::Range<usize>) -> Location {
+        lume_span::source::Location {
+            file: file.clone(),
+            index,
+        }
+        .intern()
+    }
+
+    fn node(idx: usize) -> NodeId {
+        NodeId::from_usize(PackageId::empty(), idx)
+    }
+
+    fn lookup_of(symbols: impl IntoIterator<Item = SymbolEntry>) -> SymbolLookup {
+        let mut visitor = LocationVisitor::default();
+
+        for sym in symbols {
+            visitor.insert(sym);
+        }
+
+        let mut lookup = SymbolLookup::default();
+        lookup.insert_symbols(visitor);
+
+        lookup
+    }
+
+    #[test]
+    fn more_specific_symbols_take_precedence() {
+        let file = source_file("let a = b;");
+        let loc = location(&file, 4..5);
+
+        let pattern = SymbolEntry {
+            location: loc,
+            kind: SymbolKind::Pattern { id: node(0) },
+        };
+
+        let variable = SymbolEntry {
+            location: loc,
+            kind: SymbolKind::VariableReference { id: node(1) },
+        };
+
+        for symbols in [[pattern.clone(), variable.clone()], [variable.clone(), pattern.clone()]] {
+            let lookup = lookup_of(symbols);
+
+            assert_eq!(lookup.iter().count(), 1);
+            assert_eq!(lookup.lookup_position(location(&file, 4..4)), Some(&variable));
+        }
+    }
+
+    #[test]
+    fn equal_precedence_keeps_first_symbol() {
+        let file = source_file("foo()");
+        let loc = location(&file, 0..3);
+
+        let call = SymbolEntry {
+            location: loc,
+            kind: SymbolKind::Call { id: node(0) },
+        };
+
+        let literal = SymbolEntry {
+            location: loc,
+            kind: SymbolKind::Literal { id: node(1) },
+        };
+
+        let lookup = lookup_of([call.clone(), literal]);
+
+        assert_eq!(lookup.lookup_position(location(&file, 1..1)), Some(&call));
+    }
+
+    #[test]
+    fn smallest_containing_symbol_wins() {
+        let file = source_file("foo.bar(baz)");
+
+        let call = SymbolEntry {
+            location: location(&file, 0..12),
+            kind: SymbolKind::Call { id: node(0) },
+        };
+
+        let argument = SymbolEntry {
+            location: location(&file, 8..11),
+            kind: SymbolKind::VariableReference { id: node(1) },
+        };
+
+        let lookup = lookup_of([call.clone(), argument.clone()]);
+
+        assert_eq!(lookup.lookup_position(location(&file, 9..9)), Some(&argument));
+        assert_eq!(lookup.lookup_position(location(&file, 2..2)), Some(&call));
+    }
+
+    #[test]
+    fn zero_length_symbols_are_skipped() {
+        let file = source_file("let a = b;");
+
+        let synthesized = SymbolEntry {
+            location: location(&file, 8..8),
+            kind: SymbolKind::Call { id: node(0) },
+        };
+
+        let identifier = SymbolEntry {
+            location: location(&file, 8..9),
+            kind: SymbolKind::VariableReference { id: node(1) },
+        };
+
+        let lookup = lookup_of([synthesized, identifier.clone()]);
+
+        assert_eq!(lookup.lookup_position(location(&file, 8..8)), Some(&identifier));
+    }
+
+    #[test]
+    fn positions_outside_of_symbols_find_nothing() {
+        let file = source_file("let a = b;");
+
+        let identifier = SymbolEntry {
+            location: location(&file, 8..9),
+            kind: SymbolKind::VariableReference { id: node(0) },
+        };
+
+        let lookup = lookup_of([identifier]);
+
+        assert_eq!(lookup.lookup_position(location(&file, 0..0)), None);
+        assert_eq!(lookup.lookup_position(location(&file, 10..10)), None);
+    }
+
+    #[test]
+    fn long_symbols_contain_positions_after_short_symbols() {
+        let file = source_file(&"x".repeat(100));
+
+        let block = SymbolEntry {
+            location: location(&file, 0..100),
+            kind: SymbolKind::Call { id: node(0) },
+        };
+
+        let mut symbols = vec![block.clone()];
+
+        for (idx, start) in (10..80).step_by(2).enumerate() {
+            symbols.push(SymbolEntry {
+                location: location(&file, start..start + 1),
+                kind: SymbolKind::VariableReference { id: node(idx + 1) },
+            });
+        }
+
+        let lookup = lookup_of(symbols);
+
+        assert_eq!(lookup.lookup_position(location(&file, 90..90)), Some(&block));
+        assert_eq!(lookup.lookup_position(location(&file, 5..5)), Some(&block));
+    }
+
+    /// Builds a lookup over a file with the given amount of call statements,
+    /// each of which contains a call and its argument.
+    fn benchmark_lookup(statements: usize) -> (Arc<SourceFile>, SymbolLookup) {
+        let file = source_file(&"call(arg); ".repeat(statements));
+
+        let symbols = (0..statements).flat_map(|idx| {
+            let start = idx * 11;
+
+            [
+                SymbolEntry {
+                    location: location(&file, start..start + 9),
+                    kind: SymbolKind::Call { id: node(idx * 2) },
+                },
+                SymbolEntry {
+                    location: location(&file, start + 5..start + 8),
+                    kind: SymbolKind::VariableReference { id: node(idx * 2 + 1) },
+                },
+            ]
+        });
+
+        let lookup = lookup_of(symbols);
+
+        (file, lookup)
+    }
+
+    fn linear_lookup_position(lookup: &SymbolLookup, location: Location) -> Option<&SymbolEntry> {
+        lookup
+            .symbols_in_file(location.file.id)
+            .filter(|sym| {
+                !sym.location.index.is_empty()
+                    && sym.location.start() <= location.index.start
+                    && sym.location.end() >= location.index.start
+            })
+            .min_by_key(|sym| sym.location.index.len())
+    }
+
+    #[test]
+    fn lookup_matches_linear_scan() {
+        let (file, lookup) = benchmark_lookup(100);
+
+        for idx in 0..file.content.len() {
+            let location = location(&file, idx..idx);
+
+            assert_eq!(
+                lookup.lookup_position(location).map(|sym| sym.location.index.len()),
+                linear_lookup_position(&lookup, location).map(|sym| sym.location.index.len()),
+                "at {idx}"
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored`"]
+    fn benchmark_lookup_position() {
+        let (file, lookup) = benchmark_lookup(5_000);
+        let positions = (0..file.content.len())
+            .step_by(7)
+            .map(|idx| location(&file, idx..idx))
+            .collect::<Vec<_>>();
+
+        let start = std::time::Instant::now();
+        let found = positions.iter().filter_map(|loc| lookup.lookup_position(*loc)).count();
+        let search = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let expected = positions
+            .iter()
+            .filter_map(|loc| linear_lookup_position(&lookup, *loc))
+            .count();
+        let linear = start.elapsed();
+
+        assert_eq!(found, expected);
+
+        log::info!(
+            "{} lookups over {} symbols: binary search {search:?}, linear scan {linear:?}",
+            positions.len(),
+            lookup.iter().count()
+        );
+
+        // Each linear lookup visits all symbols, while a binary search only visits the
+        // symbols around the location, so it must be faster by a wide margin.
+        assert!(search * 10 < linear, "binary search {search:?}, linear scan {linear:?}");
+    }
+
+    fn named_source_file(name: &str, content: &str) -> Arc<SourceFile> {
+        Arc::new(SourceFile::new(
+            PackageId::empty(),
+            FileName::Real(std::path::PathBuf::from(name)),
+            content.to_owned(),
+        ))
+    }
+
+    #[test]
+    fn unchanged_packages_reuse_their_lookup() {
+        let (mut state, _messages, workspace) = checked_test_state(&["a", "b"], &[
+            ("a/Arcfile", &test_arcfile("a", "")),
+            ("a/src/lib.lm", "fn add() {}\n"),
+            ("b/Arcfile", &test_arcfile("b", "")),
+            ("b/src/main.lm", "fn main() {}\n"),
+        ]);
+
+        let package_a = state.source_of_uri(&workspace.uri_of("a/src/lib.lm")).unwrap().package;
+        let package_b = state.source_of_uri(&workspace.uri_of("b/src/main.lm")).unwrap().package;
+
+        let mut symbols = WorkspaceSymbols::default();
+        symbols.update(&state.checked.graph);
+
+        // The symbols of a reused lookup are never reallocated, while rebuilding a
+        // lookup always allocates new storage for them.
+        let storage_of = |symbols: &WorkspaceSymbols, package| {
+            let package = &symbols.packages[&package];
+            let file = package.lookup.files.values().next().unwrap();
+
+            (package.source_hash, file.symbols.as_ptr())
+        };
+
+        let (hash_a, storage_a) = storage_of(&symbols, package_a);
+        let (hash_b, storage_b) = storage_of(&symbols, package_b);
+
+        replace_test_document(
+            &mut state,
+            &workspace.uri_of("b/src/main.lm"),
+            "fn main() {}\n\nfn other() {}\n",
+        );
+        state.compile_workspace();
+
+        symbols.update(&state.checked.graph);
+
+        assert_eq!(storage_of(&symbols, package_a), (hash_a, storage_a));
+
+        let (edited_hash_b, edited_storage_b) = storage_of(&symbols, package_b);
+        assert_ne!(edited_hash_b, hash_b);
+        assert_ne!(edited_storage_b, storage_b);
+    }
+
+    #[test]
+    fn renamed_files_change_the_hash() {
+        let original = [named_source_file("main.lm", "fn main() {}")];
+        let renamed = [named_source_file("app.lm", "fn main() {}")];
+
+        assert_ne!(hash_sources(original.iter()), hash_sources(renamed.iter()));
+    }
+
+    /// Builds the files of a workspace with the given amount of independent
+    /// packages, each of which contains the given amount of source files.
+    fn benchmark_workspace(packages: usize, files: usize) -> (Vec<String>, Vec<(String, String)>) {
+        let mut roots = Vec::new();
+        let mut sources = Vec::new();
+
+        for package in 0..packages {
+            let root = format!("package{package}");
+
+            sources.push((format!("{root}/Arcfile"), test_arcfile(&root, "")));
+
+            for file in 0..files {
+                let functions = (0..50)
+                    .map(|idx| {
+                        format!("fn f{file}_{idx}(a: Int32, b: Int32) -> Int32 {{\n    return a + b * {idx};\n}}\n")
+                    })
+                    .collect::<String>();
+
+                sources.push((format!("{root}/src/file{file}.lm"), functions));
+            }
+
+            roots.push(root);
+        }
+
+        (roots, sources)
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored`"]
+    fn benchmark_parallel_indexing() {
+        let (roots, sources) = benchmark_workspace(24, 4);
+        let roots = roots.iter().map(String::as_str).collect::<Vec<_>>();
+        let sources = sources
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.as_str()))
+            .collect::<Vec<_>>();
+
+        let (state, _messages, _workspace) = checked_test_state(&roots, &sources);
+        let graph = &state.checked.graph;
+
+        let start = std::time::Instant::now();
+        for package in graph.packages.values() {
+            SymbolLookup::from_hir(package.tcx.hir()).unwrap();
+        }
+        let sequential = start.elapsed();
+
+        let start = std::time::Instant::now();
+        WorkspaceSymbols::default().update(graph);
+        let parallel = start.elapsed();
+
+        log::info!(
+            "indexing {} packages: sequential {sequential:?}, parallel {parallel:?}",
+            graph.packages.len()
+        );
+
+        assert!(
+            parallel <= sequential,
+            "parallel indexing {parallel:?} is slower than sequential indexing {sequential:?}"
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored`"]
+    fn benchmark_single_file_update() {
+        let (roots, sources) = benchmark_workspace(1, 40);
+        let roots = roots.iter().map(String::as_str).collect::<Vec<_>>();
+        let sources = sources
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.as_str()))
+            .collect::<Vec<_>>();
+
+        let (mut state, _messages, workspace) = checked_test_state(&roots, &sources);
+
+        let mut symbols = WorkspaceSymbols::default();
+        symbols.update(&state.checked.graph);
+
+        // Appending to the last file keeps the node IDs of all other files intact.
+        let uri = workspace.uri_of("package0/src/file39.lm");
+        let content = format!(
+            "{}fn appended() {{}}\n",
+            state.vfs.get_document(&uri).unwrap().file.content
+        );
+
+        replace_test_document(&mut state, &uri, &content);
+        state.compile_workspace();
+
+        let graph = &state.checked.graph;
+
+        let start = std::time::Instant::now();
+        for package in graph.packages.values() {
+            SymbolLookup::from_hir(package.tcx.hir()).unwrap();
+        }
+        let full = start.elapsed();
+
+        let start = std::time::Instant::now();
+        symbols.update(graph);
+        let incremental = start.elapsed();
+
+        log::info!("updating after a single-file edit: full rebuild {full:?}, incremental {incremental:?}");
+
+        assert!(
+            incremental <= full,
+            "incremental update {incremental:?} is slower than full rebuild {full:?}"
+        );
+    }
+}
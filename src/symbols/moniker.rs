@@ -0,0 +1,83 @@
+use lsp_types::{Moniker, MonikerKind, UniquenessLevel};
+use lume_errors::Result;
+use lume_span::Location;
+
+use crate::state::State;
+use crate::symbols::definition::definition_of_symbol;
+use crate::symbols::lookup::SymbolKind;
+
+/// Scheme of all monikers created by the server.
+const MONIKER_SCHEME: &str = "lume";
+
+/// Gets the moniker of the symbol at the given location, which identifies the
+/// symbol across packages by the name of its package and its fully-qualified
+/// path.
+///
+/// Symbols declared within the same package are exported, while symbols
+/// declared in other packages are imported. Local symbols, such as variables,
+/// and symbols which cannot be resolved have no moniker.
+pub(crate) fn moniker_of(state: &State, location: Location) -> Result<Option<Moniker>> {
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        return Ok(None);
+    };
+
+    let Some(package) = state.checked.graph.packages.get(&sym.location.file.package) else {
+        return Ok(None);
+    };
+
+    let path = match &sym.kind {
+        SymbolKind::Type { name } | SymbolKind::Variant { name } => format!("{name:+}"),
+        SymbolKind::Callable { reference } => format!("{:+}", package.tcx.callable_of(*reference)?.name()),
+        SymbolKind::Call { id } => {
+            let Some(expr) = package.tcx.hir_call_expr(*id) else {
+                return Ok(None);
+            };
+
+            format!("{:+}", package.tcx.probe_callable(expr)?.name())
+        }
+        SymbolKind::Field { id } => {
+            let Some(lume_hir::Node::Field(field)) = package.tcx.hir_node(*id) else {
+                return Ok(None);
+            };
+
+            let struct_def = package.tcx.owning_struct_of_field(*id)?;
+
+            format!("{:+}::{}", struct_def.name, field.name)
+        }
+        SymbolKind::Member { callee, field } => {
+            let callee_type = package.tcx.type_of(*callee)?;
+            let Some(callee_type_def) = package.tcx.tdb().type_(callee_type.instance_of) else {
+                return Ok(None);
+            };
+
+            format!("{:+}::{}", callee_type_def.name, field)
+        }
+        SymbolKind::Pattern { .. }
+        | SymbolKind::Literal { .. }
+        | SymbolKind::VariableReference { .. }
+        | SymbolKind::TypeParameter { .. } => {
+            return Ok(None);
+        }
+    };
+
+    let Some(declaration) = definition_of_symbol(state, sym)? else {
+        return Ok(None);
+    };
+
+    let Some(declaring_package) = state.checked.graph.packages.get(&declaration.file.package) else {
+        return Ok(None);
+    };
+
+    let kind = if declaration.file.package == location.file.package {
+        MonikerKind::Export
+    } else {
+        MonikerKind::Import
+    };
+
+    Ok(Some(Moniker {
+        scheme: MONIKER_SCHEME.to_owned(),
+        identifier: format!("{}::{path}", declaring_package.package),
+        unique: UniquenessLevel::Scheme,
+        kind: Some(kind),
+    }))
+}
@@ -0,0 +1,228 @@
+use lsp_types::{DocumentSymbol, SymbolKind};
+use lume_errors::Result;
+use lume_hir::WithLocation as _;
+use lume_span::{Location, SourceFile};
+
+use crate::position::{PositionEncoding, lsp_range_of};
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Named declaration within a source file, such as a function, type or method.
+#[derive(Debug, Clone)]
+pub(crate) struct Declaration {
+    pub name: String,
+    pub detail: Option<String>,
+    pub kind: SymbolKind,
+    pub location: Location,
+
+    /// Name of the type or namespace which contains the declaration, if any.
+    pub container: Option<String>,
+
+    pub children: Vec<Declaration>,
+}
+
+/// Collects all top-level declarations within the given HIR map, along with
+/// their members.
+///
+/// If `file` is given, only declarations which are defined within that file
+/// are returned.
+pub(crate) fn declarations_of(hir: &lume_hir::Map, file: Option<&SourceFile>) -> Result<Vec<Declaration>> {
+    let mut visitor = OutlineVisitor {
+        file,
+        declarations: Vec::new(),
+    };
+
+    traverse(hir, &mut visitor)?;
+
+    let mut declarations = visitor.declarations;
+    declarations.sort_by_key(|decl| decl.location.index.start);
+
+    Ok(declarations)
+}
+
+/// Builds the hierarchical outline of all declarations within the given file.
+pub(crate) fn document_symbols_of(
+    hir: &lume_hir::Map,
+    file: &SourceFile,
+    encoding: PositionEncoding,
+) -> Result<Vec<DocumentSymbol>> {
+    let declarations = declarations_of(hir, Some(file))?;
+
+    Ok(declarations
+        .into_iter()
+        .map(|decl| document_symbol(decl, encoding))
+        .collect())
+}
+
+struct OutlineVisitor<'a> {
+    file: Option<&'a SourceFile>,
+    declarations: Vec<Declaration>,
+}
+
+impl Visitor for OutlineVisitor<'_> {
+    fn visit_node(&mut self, node: &lume_hir::Node) -> Result<()> {
+        let declaration = match node {
+            lume_hir::Node::Function(func) => Declaration {
+                name: func.name.to_string(),
+                detail: None,
+                kind: SymbolKind::FUNCTION,
+                location: func.name.location,
+                container: func.name.clone().parent().map(|parent| format!("{parent:+}")),
+                children: Vec::new(),
+            },
+            lume_hir::Node::Type(lume_hir::TypeDefinition::Struct(struct_def)) => {
+                let container = struct_def.name.to_string();
+
+                let children = struct_def
+                    .fields
+                    .iter()
+                    .map(|field| Declaration {
+                        name: field.name.to_string(),
+                        detail: Some(field.field_type.to_string()),
+                        kind: SymbolKind::FIELD,
+                        location: field.name.location,
+                        container: Some(container.clone()),
+                        children: Vec::new(),
+                    })
+                    .collect();
+
+                Declaration {
+                    name: struct_def.name.to_string(),
+                    detail: None,
+                    kind: SymbolKind::STRUCT,
+                    location: struct_def.name().location(),
+                    container: struct_def.name.clone().parent().map(|parent| format!("{parent:+}")),
+                    children,
+                }
+            }
+            lume_hir::Node::Type(lume_hir::TypeDefinition::Trait(trait_def)) => {
+                let container = trait_def.name.to_string();
+
+                let children = trait_def
+                    .methods
+                    .iter()
+                    .map(|method| method_declaration(&method.name, &container))
+                    .collect();
+
+                Declaration {
+                    name: trait_def.name.to_string(),
+                    detail: None,
+                    kind: SymbolKind::INTERFACE,
+                    location: trait_def.name().location(),
+                    container: trait_def.name.clone().parent().map(|parent| format!("{parent:+}")),
+                    children,
+                }
+            }
+            lume_hir::Node::Type(lume_hir::TypeDefinition::Enum(enum_def)) => {
+                let container = enum_def.name.to_string();
+
+                let children = enum_def
+                    .cases
+                    .iter()
+                    .map(|case| Declaration {
+                        name: case.name.to_string(),
+                        detail: None,
+                        kind: SymbolKind::ENUM_MEMBER,
+                        location: case.name.location(),
+                        container: Some(container.clone()),
+                        children: Vec::new(),
+                    })
+                    .collect();
+
+                Declaration {
+                    name: enum_def.name.to_string(),
+                    detail: None,
+                    kind: SymbolKind::ENUM,
+                    location: enum_def.name().location(),
+                    container: enum_def.name.clone().parent().map(|parent| format!("{parent:+}")),
+                    children,
+                }
+            }
+            lume_hir::Node::Impl(type_impl) => {
+                let container = type_impl.target.to_string();
+
+                let children = type_impl
+                    .methods
+                    .iter()
+                    .map(|method| method_declaration(&method.name, &container))
+                    .collect();
+
+                Declaration {
+                    name: format!("impl {}", type_impl.target),
+                    detail: None,
+                    kind: SymbolKind::CLASS,
+                    location: type_impl.target.location,
+                    container: None,
+                    children,
+                }
+            }
+            lume_hir::Node::TraitImpl(trait_impl) => {
+                let container = trait_impl.target.to_string();
+
+                let children = trait_impl
+                    .methods
+                    .iter()
+                    .map(|method| method_declaration(&method.name, &container))
+                    .collect();
+
+                Declaration {
+                    name: format!("use {} in {}", trait_impl.name, trait_impl.target),
+                    detail: None,
+                    kind: SymbolKind::CLASS,
+                    location: trait_impl.name.location,
+                    container: None,
+                    children,
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        if let Some(file) = self.file
+            && declaration.location.file.id != file.id
+        {
+            return Ok(());
+        }
+
+        self.declarations.push(declaration);
+
+        Ok(())
+    }
+}
+
+fn method_declaration(name: &lume_hir::Identifier, container: &str) -> Declaration {
+    Declaration {
+        name: name.to_string(),
+        detail: None,
+        kind: SymbolKind::METHOD,
+        location: name.location,
+        container: Some(container.to_owned()),
+        children: Vec::new(),
+    }
+}
+
+#[allow(deprecated)]
+fn document_symbol(declaration: Declaration, encoding: PositionEncoding) -> DocumentSymbol {
+    let range = lsp_range_of(declaration.location, encoding);
+
+    let children = if declaration.children.is_empty() {
+        None
+    } else {
+        Some(
+            declaration
+                .children
+                .into_iter()
+                .map(|child| document_symbol(child, encoding))
+                .collect(),
+        )
+    };
+
+    DocumentSymbol {
+        name: declaration.name,
+        detail: declaration.detail,
+        kind: declaration.kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range: range,
+        children,
+    }
+}
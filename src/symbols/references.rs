@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use lsp_types::{DocumentHighlight, DocumentHighlightKind};
+use lume_errors::Result;
+use lume_span::{Location, NodeId};
+
+use crate::state::State;
+use crate::symbols::definition::{definition_of, definition_of_symbol};
+use crate::symbols::lookup::{SymbolEntry, SymbolKind};
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Finds all references to the symbol at the given location, across all
+/// packages in the workspace.
+///
+/// The declaration of the symbol is only included in the returned list if
+/// `include_declaration` is `true`.
+pub(crate) fn references_of(state: &State, location: Location, include_declaration: bool) -> Result<Vec<Location>> {
+    let Some(declaration) = definition_of(state, location)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut references = Vec::new();
+
+    if include_declaration {
+        references.push(declaration);
+    }
+
+    let symbols = referencing_symbols(state, declaration, state.checked.symbols.iter());
+    references.extend(symbols.into_iter().map(|sym| sym.location));
+
+    Ok(references)
+}
+
+/// Finds all occurences of the symbol at the given location within the same
+/// file.
+///
+/// Variable references which are the target of an assignment are marked as
+/// writes, whereas all other occurences are marked as reads.
+pub(crate) fn document_highlights(state: &State, location: Location) -> Result<Vec<DocumentHighlight>> {
+    let Some(declaration) = definition_of(state, location)? else {
+        return Ok(Vec::new());
+    };
+
+    let Some(package) = state.package_of(location.file.package) else {
+        return Ok(Vec::new());
+    };
+    let assignment_targets = assignment_targets(package.tcx.hir())?;
+
+    let mut highlights = Vec::new();
+
+    if declaration.file.id == location.file.id {
+        highlights.push(DocumentHighlight {
+            range: state.lsp_range_of(declaration),
+            kind: Some(DocumentHighlightKind::READ),
+        });
+    }
+
+    for sym in referencing_symbols(
+        state,
+        declaration,
+        state.checked.symbols.symbols_in_file(&location.file),
+    ) {
+        let kind = match sym.kind {
+            SymbolKind::VariableReference { id } if assignment_targets.contains(&id) => DocumentHighlightKind::WRITE,
+            _ => DocumentHighlightKind::READ,
+        };
+
+        highlights.push(DocumentHighlight {
+            range: state.lsp_range_of(sym.location),
+            kind: Some(kind),
+        });
+    }
+
+    Ok(highlights)
+}
+
+/// Finds the locations of all occurrences of the symbol at the given location
+/// within the same file, including its declaration if it's within the file.
+pub(crate) fn occurrences_in_file(state: &State, location: Location) -> Result<Vec<Location>> {
+    let Some(declaration) = definition_of(state, location)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut occurrences = Vec::new();
+
+    if declaration.file.id == location.file.id {
+        occurrences.push(declaration);
+    }
+
+    let symbols = referencing_symbols(
+        state,
+        declaration,
+        state.checked.symbols.symbols_in_file(&location.file),
+    );
+
+    occurrences.extend(symbols.into_iter().map(|sym| sym.location));
+
+    Ok(occurrences)
+}
+
+/// Filters the given symbols down to the symbols which refer to the given
+/// declaration, excluding the declaration itself.
+fn referencing_symbols<'a>(
+    state: &State,
+    declaration: Location,
+    symbols: impl Iterator<Item = &'a SymbolEntry>,
+) -> Vec<&'a SymbolEntry> {
+    let mut references = Vec::new();
+
+    for sym in symbols {
+        if sym.location == declaration {
+            continue;
+        }
+
+        // Since most symbols are not related to the declaration, errors are expected
+        // and can safely be ignored.
+        match definition_of_symbol(state, sym) {
+            Ok(Some(definition)) if definition == declaration => references.push(sym),
+            Ok(_) => {}
+            Err(err) => log::debug!("could not resolve symbol at {}: {}", sym.location, err.message()),
+        }
+    }
+
+    references
+}
+
+/// Collects the IDs of all expressions which are the target of an assignment.
+pub(crate) fn assignment_targets(hir: &lume_hir::Map) -> Result<HashSet<NodeId>> {
+    let mut visitor = AssignmentVisitor::default();
+    traverse(hir, &mut visitor)?;
+
+    Ok(visitor.targets)
+}
+
+#[derive(Default)]
+struct AssignmentVisitor {
+    targets: HashSet<NodeId>,
+}
+
+impl Visitor for AssignmentVisitor {
+    fn visit_expr(&mut self, expr: &lume_hir::Expression) -> Result<()> {
+        if let lume_hir::ExpressionKind::Assignment(assignment) = &expr.kind {
+            self.targets.insert(assignment.target);
+        }
+
+        Ok(())
+    }
+}
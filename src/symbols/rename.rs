@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use lsp_types::{LinkedEditingRanges, TextEdit, WorkspaceEdit};
+use lume_errors::Result;
+use lume_span::Location;
+
+use crate::state::State;
+use crate::symbols::definition::definition_of_symbol;
+use crate::symbols::lookup::SymbolKind;
+use crate::symbols::references::{occurrences_in_file, references_of};
+
+/// Reserved keywords in Lume, which cannot be used as identifiers.
+const KEYWORDS: &[&str] = &[
+    "as",
+    "break",
+    "builtin",
+    "continue",
+    "else",
+    "enum",
+    "false",
+    "fn",
+    "for",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "is",
+    "let",
+    "loop",
+    "namespace",
+    "pub",
+    "return",
+    "self",
+    "struct",
+    "switch",
+    "trait",
+    "true",
+    "use",
+    "while",
+];
+
+/// Pattern of legal identifiers in Lume, as a regular expression which is
+/// evaluated by the client.
+const IDENTIFIER_PATTERN: &str = "[a-zA-Z_][a-zA-Z0-9_]*";
+
+/// Determines whether the given name is a legal identifier in Lume, matching
+/// [`IDENTIFIER_PATTERN`].
+pub(crate) fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    let Some(first) = chars.next() else {
+        return false;
+    };
+
+    if !(first.is_ascii_alphabetic() || first == '_') {
+        return false;
+    }
+
+    if !chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_') {
+        return false;
+    }
+
+    !KEYWORDS.contains(&name)
+}
+
+/// Gets the location of the identifier which would be renamed, if a rename
+/// were to be requested at the given location.
+///
+/// If the location refers to a literal, a keyword or some symbol which cannot
+/// be resolved, returns [`None`].
+pub(crate) fn prepare_rename(state: &State, location: Location) -> Result<Option<Location>> {
+    let Some(sym) = state.checked.symbols.lookup_position(location) else {
+        return Ok(None);
+    };
+
+    if matches!(sym.kind, SymbolKind::Literal { .. }) {
+        return Ok(None);
+    }
+
+    // Some symbols, such as intrinsic calls, span entire expressions instead of
+    // a single identifier, which cannot be renamed.
+    let text = &sym.location.file.content[sym.location.index.clone()];
+    if !is_valid_identifier(text) {
+        return Ok(None);
+    }
+
+    if definition_of_symbol(state, sym)?.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(sym.location))
+}
+
+/// Builds the edits required to rename the symbol at the given location, as
+/// well as all references to it, to the given name.
+pub(crate) fn rename_edits(state: &State, location: Location, new_name: &str) -> Result<WorkspaceEdit> {
+    let mut changes: HashMap<_, Vec<TextEdit>> = HashMap::new();
+
+    for reference in references_of(state, location, true)? {
+        let Some(location) = state.lsp_location_of(reference) else {
+            continue;
+        };
+
+        changes.entry(location.uri).or_default().push(TextEdit {
+            range: location.range,
+            new_text: new_name.to_owned(),
+        });
+    }
+
+    Ok(WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    })
+}
+
+/// Gets the ranges of all occurrences of the symbol at the given location
+/// within the same file, which can be edited simultaneously.
+///
+/// If the location refers to a literal, a keyword or some symbol which cannot
+/// be resolved, returns [`None`].
+pub(crate) fn linked_editing_ranges(state: &State, location: Location) -> Result<Option<LinkedEditingRanges>> {
+    let Some(name_location) = prepare_rename(state, location)? else {
+        return Ok(None);
+    };
+
+    let name = &name_location.file.content[name_location.index.clone()];
+
+    // Only occurrences which are spelled exactly like the symbol can be edited,
+    // since some symbols span more than a single identifier.
+    let ranges = occurrences_in_file(state, location)?
+        .into_iter()
+        .filter(|occurrence| occurrence.file.content.get(occurrence.index.clone()) == Some(name))
+        .map(|occurrence| state.lsp_range_of(occurrence))
+        .collect();
+
+    Ok(Some(LinkedEditingRanges {
+        ranges,
+        word_pattern: Some(IDENTIFIER_PATTERN.to_owned()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_identifiers() {
+        for name in ["a", "_", "_private", "snake_case", "camelCase", "Type2", "x_1"] {
+            assert!(is_valid_identifier(name), "{name} should be valid");
+        }
+    }
+
+    #[test]
+    fn invalid_identifiers() {
+        for name in ["", "1a", "a-b", "a b", "a.b", "a::b", "café", "λ"] {
+            assert!(!is_valid_identifier(name), "{name} should be invalid");
+        }
+    }
+
+    #[test]
+    fn keywords_are_invalid() {
+        for keyword in KEYWORDS {
+            assert!(!is_valid_identifier(keyword), "{keyword} should be invalid");
+        }
+    }
+}
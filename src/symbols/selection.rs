@@ -0,0 +1,111 @@
+use lsp_types::{Position, SelectionRange};
+use lume_errors::Result;
+use lume_hir::WithLocation as _;
+use lume_span::{Location, SourceFile};
+
+use crate::position::{PositionEncoding, index_from_position, lsp_range_of};
+use crate::symbols::visitor::{Visitor, traverse};
+
+/// Builds the chain of selection ranges for each of the given positions, from
+/// the innermost identifier or expression outwards to the enclosing
+/// declaration.
+///
+/// Positions which are not contained within any node yield a single, empty
+/// selection range at the position itself, since the response must contain
+/// an entry for every requested position.
+pub(crate) fn selection_ranges_of(
+    hir: &lume_hir::Map,
+    file: &SourceFile,
+    positions: &[Position],
+    encoding: PositionEncoding,
+) -> Result<Vec<SelectionRange>> {
+    let mut visitor = SelectionVisitor {
+        file,
+        locations: Vec::new(),
+    };
+
+    traverse(hir, &mut visitor)?;
+
+    // Nodes often share the same span as their parent, such as an expression
+    // statement and its expression, which would yield redundant selections.
+    let mut locations = visitor.locations;
+    locations.sort_by_key(|location| (location.index.start, std::cmp::Reverse(location.index.end)));
+    locations.dedup_by_key(|location| location.index.clone());
+
+    let ranges = positions
+        .iter()
+        .map(|position| {
+            let index = index_from_position(
+                &file.content,
+                position.line as usize,
+                position.character as usize,
+                encoding,
+            );
+
+            let mut containing = locations
+                .iter()
+                .filter(|location| location.index.start <= index && location.index.end >= index)
+                .collect::<Vec<_>>();
+
+            // Nest each span within the previous span, from outermost to innermost.
+            containing.sort_by_key(|location| std::cmp::Reverse(location.index.len()));
+
+            let mut selection: Option<SelectionRange> = None;
+
+            for location in containing {
+                selection = Some(SelectionRange {
+                    range: lsp_range_of(*location, encoding),
+                    parent: selection.map(Box::new),
+                });
+            }
+
+            selection.unwrap_or(SelectionRange {
+                range: lsp_types::Range::new(*position, *position),
+                parent: None,
+            })
+        })
+        .collect();
+
+    Ok(ranges)
+}
+
+/// Collects the locations of all identifiers, expressions, statements and
+/// declarations within a single file.
+struct SelectionVisitor<'a> {
+    file: &'a SourceFile,
+    locations: Vec<Location>,
+}
+
+impl SelectionVisitor<'_> {
+    fn push(&mut self, location: Location) {
+        if location.file.id == self.file.id && !location.index.is_empty() {
+            self.locations.push(location);
+        }
+    }
+}
+
+impl Visitor for SelectionVisitor<'_> {
+    fn visit_node(&mut self, node: &lume_hir::Node) -> Result<()> {
+        self.push(node.location());
+
+        Ok(())
+    }
+
+    fn visit_stmt(&mut self, stmt: &lume_hir::Statement) -> Result<()> {
+        self.push(stmt.location);
+
+        Ok(())
+    }
+
+    fn visit_expr(&mut self, expr: &lume_hir::Expression) -> Result<()> {
+        self.push(expr.location);
+
+        Ok(())
+    }
+
+    fn visit_identifier(&mut self, ident: &lume_hir::Identifier) -> Result<()> {
+        self.push(ident.location);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+
+use lsp_types::{Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend};
+use lume_infer::query::CallReference;
+use lume_span::{Location, SourceFile};
+
+use crate::position::{PositionEncoding, index_from_position, position_from_index};
+use crate::state::State;
+use crate::symbols::lookup::{SymbolEntry, SymbolKind};
+use crate::symbols::references::assignment_targets;
+
+/// Token types which are advertised to the client, in the same order as
+/// [`TokenType`].
+const TOKEN_TYPES: [SemanticTokenType; 7] = [
+    SemanticTokenType::TYPE,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::METHOD,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::ENUM_MEMBER,
+    SemanticTokenType::PROPERTY,
+];
+
+/// Index of each token type within [`TOKEN_TYPES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenType {
+    Type = 0,
+    Function = 1,
+    Method = 2,
+    Parameter = 3,
+    Variable = 4,
+    EnumMember = 5,
+    Property = 6,
+}
+
+/// Token modifiers which are advertised to the client, in the same order as
+/// [`TokenModifier`].
+const TOKEN_MODIFIERS: [SemanticTokenModifier; 4] = [
+    SemanticTokenModifier::DECLARATION,
+    SemanticTokenModifier::DEFINITION,
+    SemanticTokenModifier::READONLY,
+    SemanticTokenModifier::STATIC,
+];
+
+/// Index of each token modifier within [`TOKEN_MODIFIERS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenModifier {
+    Declaration = 0,
+    Definition = 1,
+    Readonly = 2,
+    Static = 3,
+}
+
+impl TokenModifier {
+    /// Gets the bit of the modifier within the modifier bitset of a token.
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Gets the legend of all semantic tokens which the server can emit.
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Builds the semantic tokens of all symbols within the given file, encoded in
+/// the relative format expected by the client.
+///
+/// If semantic tokens are disabled within the configuration, no tokens are
+/// returned.
+pub(crate) fn semantic_tokens_of(state: &State, file: &SourceFile) -> Vec<SemanticToken> {
+    semantic_tokens_within(state, file, 0..file.content.len())
+}
+
+/// Builds the semantic tokens of all symbols which intersect the given range
+/// within the file, such as the viewport of the client.
+///
+/// Like [`semantic_tokens_of`], no tokens are returned if semantic tokens are
+/// disabled within the configuration.
+pub(crate) fn semantic_tokens_in_range(state: &State, file: &SourceFile, range: Range) -> Vec<SemanticToken> {
+    let content = file.content.as_str();
+    let encoding = state.position_encoding;
+
+    let range_start = index_from_position(
+        content,
+        range.start.line as usize,
+        range.start.character as usize,
+        encoding,
+    );
+
+    let range_end = index_from_position(content, range.end.line as usize, range.end.character as usize, encoding);
+
+    semantic_tokens_within(state, file, range_start..range_end)
+}
+
+fn semantic_tokens_within(state: &State, file: &SourceFile, span: std::ops::Range<usize>) -> Vec<SemanticToken> {
+    if !state.config.enable_semantic_tokens {
+        return Vec::new();
+    }
+
+    let reassigned = reassigned_variables(state, file);
+
+    let mut tokens = state
+        .checked
+        .symbols
+        .symbols_in_file(file)
+        .filter(|sym| sym.location.index.start < span.end && sym.location.index.end > span.start)
+        .filter_map(|sym| {
+            let (token_type, modifiers) = classify(state, sym, &reassigned)?;
+
+            Some((sym.location, token_type, modifiers))
+        })
+        .collect::<Vec<_>>();
+
+    tokens.sort_by_key(|(location, _, _)| (location.index.start, location.index.end));
+
+    encode_tokens(&tokens, state.position_encoding)
+}
+
+/// Gets the locations of the names of all variable declarations within the
+/// given file, which are the target of an assignment after being declared.
+fn reassigned_variables(state: &State, file: &SourceFile) -> HashSet<Location> {
+    let Some(package) = state.checked.graph.packages.get(&file.package) else {
+        return HashSet::new();
+    };
+
+    let targets = match assignment_targets(package.tcx.hir()) {
+        Ok(targets) => targets,
+        Err(err) => {
+            log::debug!(
+                "could not find assignments in {}: {}",
+                file.name.to_pathbuf().display(),
+                err.message()
+            );
+            return HashSet::new();
+        }
+    };
+
+    targets
+        .into_iter()
+        .filter_map(|target| match &package.tcx.hir_expr(target)?.kind {
+            lume_hir::ExpressionKind::Variable(variable_ref) => match &variable_ref.reference {
+                lume_hir::VariableSource::Variable(var_decl) => Some(var_decl.name.location),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Classifies the given symbol into a token type and a bitset of modifiers, if
+/// it should be highlighted.
+///
+/// Variables which are never reassigned are marked as readonly, based on the
+/// given set of reassigned variable declarations.
+fn classify(state: &State, sym: &SymbolEntry, reassigned: &HashSet<Location>) -> Option<(TokenType, u32)> {
+    let package = state.checked.graph.packages.get(&sym.location.file.package)?;
+
+    let declaration = TokenModifier::Declaration.bit();
+
+    match &sym.kind {
+        SymbolKind::Type { .. } => Some((TokenType::Type, 0)),
+        SymbolKind::TypeParameter { .. } => Some((TokenType::Type, declaration)),
+        SymbolKind::Callable { reference } => {
+            let token_type = match reference {
+                CallReference::Function(_) => TokenType::Function,
+                _ => TokenType::Method,
+            };
+
+            Some((token_type, declaration | TokenModifier::Definition.bit()))
+        }
+        SymbolKind::Call { id } => match &package.tcx.hir_expr(*id)?.kind {
+            lume_hir::ExpressionKind::StaticCall(_) => Some((TokenType::Function, TokenModifier::Static.bit())),
+            lume_hir::ExpressionKind::InstanceCall(_) => Some((TokenType::Method, 0)),
+            _ => None,
+        },
+        SymbolKind::Field { .. } => Some((TokenType::Property, declaration)),
+        SymbolKind::Member { .. } => Some((TokenType::Property, 0)),
+        SymbolKind::Variant { .. } => Some((TokenType::EnumMember, 0)),
+        SymbolKind::Pattern { id } => match package.tcx.hir_node(*id)? {
+            lume_hir::Node::Pattern(pattern) if matches!(pattern.kind, lume_hir::PatternKind::Identifier(_)) => {
+                Some((TokenType::Variable, declaration))
+            }
+            _ => None,
+        },
+        SymbolKind::VariableReference { id } => match &package.tcx.hir_expr(*id)?.kind {
+            lume_hir::ExpressionKind::Variable(variable_ref) => match &variable_ref.reference {
+                lume_hir::VariableSource::Parameter(_) => Some((TokenType::Parameter, 0)),
+                lume_hir::VariableSource::Variable(var_decl) if !reassigned.contains(&var_decl.name.location) => {
+                    Some((TokenType::Variable, TokenModifier::Readonly.bit()))
+                }
+                _ => Some((TokenType::Variable, 0)),
+            },
+            _ => None,
+        },
+        SymbolKind::Literal { .. } => None,
+    }
+}
+
+/// Encodes the given tokens into the relative format expected by the client.
+///
+/// The tokens must be sorted by their start index. Tokens which overlap with
+/// the previous token or span multiple lines are skipped, since not all clients
+/// support them.
+fn encode_tokens(tokens: &[(Location, TokenType, u32)], encoding: PositionEncoding) -> Vec<SemanticToken> {
+    let mut encoded = Vec::with_capacity(tokens.len());
+
+    let mut prev_line = 0;
+    let mut prev_start = 0;
+    let mut prev_end_index = 0;
+
+    for (location, token_type, modifiers) in tokens {
+        if location.index.start < prev_end_index || location.index.is_empty() {
+            continue;
+        }
+
+        let start = position_from_index(&location.file.content, location.index.start, encoding);
+        let end = position_from_index(&location.file.content, location.index.end, encoding);
+
+        if start.line != end.line {
+            continue;
+        }
+
+        let delta_line = start.line - prev_line;
+        let delta_start = if delta_line == 0 {
+            start.character - prev_start
+        } else {
+            start.character
+        };
+
+        encoded.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: end.character - start.character,
+            token_type: *token_type as u32,
+            token_modifiers_bitset: *modifiers,
+        });
+
+        prev_line = start.line;
+        prev_start = start.character;
+        prev_end_index = location.index.end;
+    }
+
+    encoded
+}
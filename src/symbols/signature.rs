@@ -0,0 +1,233 @@
+use lsp_types::{
+    Documentation, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel, SignatureHelp,
+    SignatureInformation, Uri,
+};
+
+use crate::position::index_from_position;
+use crate::state::State;
+use crate::symbols::hover::{documentation_text_of, signature_of_callable};
+use crate::symbols::lookup::SymbolKind;
+
+/// Gets the signature of the call expression which surrounds the given
+/// position, along with the index of the argument under the cursor.
+///
+/// The documentation comment of the callee is split into a summary of the
+/// callee and the documentation of each parameter, which are attached to the
+/// signature and parameters respectively.
+///
+/// If the cursor is not within the argument list of a call, or if the callee
+/// cannot be resolved, returns [`None`].
+pub(crate) fn signature_help_at(state: &State, uri: &Uri, line: usize, character: usize) -> Option<SignatureHelp> {
+    let document = state.vfs.get_document(uri)?;
+    let content = document.file.content.as_str();
+    let cursor = index_from_position(content, line, character, state.position_encoding);
+
+    let (open_paren, active_parameter) = enclosing_call(&content[..cursor])?;
+
+    // The name of the callee ends right before the opening parenthesis, ignoring
+    // any whitespace in between.
+    let name_end = content[..open_paren].trim_end().len();
+    let location = state.location_at(uri, name_end.checked_sub(1)?)?;
+
+    let sym = state.checked.symbols.lookup_position(location)?;
+    let SymbolKind::Call { id } = sym.kind else {
+        return None;
+    };
+
+    let package = state.checked.graph.packages.get(&location.file.package)?;
+    let expr = package.tcx.hir_call_expr(id)?;
+
+    let callable = match package.tcx.probe_callable(expr) {
+        Ok(callable) => callable,
+        Err(err) => {
+            log::debug!("could not resolve callee of call: {}", err.message());
+            return None;
+        }
+    };
+
+    let reference = callable.to_call_reference();
+
+    let label = match signature_of_callable(state, location, reference) {
+        Ok(label) => label,
+        Err(err) => {
+            log::debug!("could not render signature of callee: {}", err.message());
+            return None;
+        }
+    };
+
+    let documentation = package
+        .tcx
+        .callable_of(reference)
+        .ok()
+        .and_then(|callable| documentation_text_of(state, location, callable.id(), None))
+        .unwrap_or_default();
+
+    let (summary, parameter_docs) = split_documentation(&documentation);
+
+    let parameters = parameter_labels(&label)
+        .into_iter()
+        .map(|param| {
+            let name = param.split(':').next().unwrap_or(param).trim();
+
+            let documentation = parameter_docs
+                .iter()
+                .find(|(doc_name, _)| doc_name == name)
+                .map(|(_, doc)| markdown(doc.clone()));
+
+            ParameterInformation {
+                label: ParameterLabel::Simple(param.to_owned()),
+                documentation,
+            }
+        })
+        .collect();
+
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: (!summary.is_empty()).then(|| markdown(summary)),
+            parameters: Some(parameters),
+            active_parameter: Some(active_parameter),
+        }],
+        active_signature: Some(0),
+        active_parameter: Some(active_parameter),
+    })
+}
+
+/// Finds the unclosed opening parenthesis which precedes the end of the given
+/// text, along with the amount of arguments which precede the end of the text.
+fn enclosing_call(text: &str) -> Option<(usize, u32)> {
+    let mut depth = 0_usize;
+    let mut commas = 0;
+
+    for (idx, ch) in text.char_indices().rev() {
+        match ch {
+            ')' | ']' | '}' => depth += 1,
+            '(' | '[' | '{' if depth > 0 => depth -= 1,
+            '(' => return Some((idx, commas)),
+            '[' | '{' | ';' => return None,
+            ',' if depth == 0 => commas += 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Splits the parameter list of the given signature into the text of each
+/// parameter.
+fn parameter_labels(signature: &str) -> Vec<&str> {
+    let Some(start) = signature.find('(') else {
+        return Vec::new();
+    };
+
+    let mut labels = Vec::new();
+    let mut depth = 0_usize;
+    let mut param_start = start + 1;
+
+    for (idx, ch) in signature[start + 1..].char_indices() {
+        let idx = start + 1 + idx;
+
+        match ch {
+            '(' | '[' | '<' => depth += 1,
+            ')' if depth == 0 => {
+                labels.push(&signature[param_start..idx]);
+                break;
+            }
+            ')' | ']' | '>' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                labels.push(&signature[param_start..idx]);
+                param_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+
+    labels
+        .into_iter()
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .collect()
+}
+
+fn markdown(value: String) -> Documentation {
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    })
+}
+
+/// Splits the given documentation comment into the summary of the callable
+/// and the documentation of each of its parameters.
+///
+/// Parameters are documented as list items which start with the name of the
+/// parameter, optionally within backticks, followed by a colon:
+///
+/// ```text
+/// - `name`: description of the parameter
+/// ```
+///
+/// Indented lines which follow a parameter are appended to its documentation.
+/// Headings which only introduce the parameters are left out of the summary.
+fn split_documentation(documentation: &str) -> (String, Vec<(String, String)>) {
+    let mut summary = Vec::new();
+    let mut parameters: Vec<(String, String)> = Vec::new();
+    let mut in_parameter = false;
+
+    for line in documentation.lines() {
+        let trimmed = line.trim();
+
+        if let Some((name, doc)) = parameter_item(trimmed) {
+            parameters.push((name.to_owned(), doc.to_owned()));
+            in_parameter = true;
+            continue;
+        }
+
+        if in_parameter && line.starts_with(char::is_whitespace) && !trimmed.is_empty() {
+            if let Some((_, doc)) = parameters.last_mut() {
+                doc.push(' ');
+                doc.push_str(trimmed);
+            }
+
+            continue;
+        }
+
+        in_parameter = false;
+
+        let heading = trimmed.trim_start_matches('#').trim().to_lowercase();
+        if trimmed.starts_with('#') && (heading == "arguments" || heading == "parameters") {
+            continue;
+        }
+
+        summary.push(line);
+    }
+
+    (summary.join("\n").trim().to_owned(), parameters)
+}
+
+/// Parses a single list item which documents a parameter, returning the name
+/// of the parameter and its documentation.
+fn parameter_item(line: &str) -> Option<(&str, &str)> {
+    let item = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))?
+        .trim_start();
+
+    let (name, rest) = match item.strip_prefix('`') {
+        Some(quoted) => {
+            let end = quoted.find('`')?;
+            (&quoted[..end], &quoted[end + 1..])
+        }
+        None => {
+            let end = item.find(':')?;
+            (&item[..end], &item[end..])
+        }
+    };
+
+    if name.is_empty() || !name.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+        return None;
+    }
+
+    let doc = rest.trim_start().strip_prefix(':')?.trim();
+
+    Some((name, doc))
+}
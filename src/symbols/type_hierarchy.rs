@@ -0,0 +1,190 @@
+use lsp_types::{SymbolKind as LspSymbolKind, TypeHierarchyItem};
+use lume_hir::WithLocation as _;
+use lume_span::{Location, NodeId};
+
+use crate::state::State;
+use crate::symbols::definition::location_of_type_def;
+use crate::symbols::lookup::SymbolKind;
+
+/// Resolves the type at the given location into a type hierarchy item.
+pub(crate) fn prepare_type_hierarchy(state: &State, location: Location) -> Option<TypeHierarchyItem> {
+    let type_id = type_at(state, location)?;
+
+    type_hierarchy_item(state, type_id).or_else(|| builtin_type_hierarchy_item(state, location))
+}
+
+/// Finds the supertypes of the type at the given location.
+///
+/// The supertypes of a trait are the constraints on its type parameters, while
+/// the supertypes of structs and enums are all the traits they implement.
+pub(crate) fn supertypes_of(state: &State, location: Location) -> Vec<TypeHierarchyItem> {
+    let Some(type_id) = type_at(state, location) else {
+        return Vec::new();
+    };
+
+    let mut supertypes = Vec::new();
+
+    for package in state.checked.graph.packages.values() {
+        let tdb = package.tcx.tdb();
+
+        if let Some(lume_hir::Node::Type(lume_hir::TypeDefinition::Trait(trait_def))) = package.tcx.hir_node(type_id) {
+            for type_param in trait_def.type_parameters.iter() {
+                supertypes.extend(
+                    type_param
+                        .constraints
+                        .iter()
+                        .filter_map(|constraint| tdb.find_type(&constraint.name).map(|ty| ty.id)),
+                );
+            }
+        }
+    }
+
+    supertypes.extend(implemented_traits_of(state, type_id));
+
+    items_of(state, supertypes)
+}
+
+/// Finds the subtypes of the type at the given location, which are all types
+/// which implement the trait. Structs and enums have no subtypes.
+pub(crate) fn subtypes_of(state: &State, location: Location) -> Vec<TypeHierarchyItem> {
+    let Some(type_id) = type_at(state, location) else {
+        return Vec::new();
+    };
+
+    let mut subtypes = Vec::new();
+
+    // Implementations can be declared in any package, so all packages within the
+    // workspace must be searched.
+    for package in state.checked.graph.packages.values() {
+        let tdb = package.tcx.tdb();
+
+        for node in package.tcx.hir().nodes().values() {
+            let lume_hir::Node::TraitImpl(trait_impl) = node else {
+                continue;
+            };
+
+            if tdb.find_type(&trait_impl.name.name).map(|ty| ty.id) == Some(type_id)
+                && let Some(target_type) = tdb.find_type(&trait_impl.target.name)
+            {
+                subtypes.push(target_type.id);
+            }
+        }
+    }
+
+    items_of(state, subtypes)
+}
+
+/// Gets the IDs of all traits which are implemented by the type with the
+/// given ID.
+pub(crate) fn implemented_traits_of(state: &State, type_id: NodeId) -> Vec<NodeId> {
+    let mut traits = Vec::new();
+
+    // Implementations can be declared in any package, so all packages within the
+    // workspace must be searched.
+    for package in state.checked.graph.packages.values() {
+        let tdb = package.tcx.tdb();
+
+        for node in package.tcx.hir().nodes().values() {
+            let lume_hir::Node::TraitImpl(trait_impl) = node else {
+                continue;
+            };
+
+            if tdb.find_type(&trait_impl.target.name).map(|ty| ty.id) == Some(type_id)
+                && let Some(trait_type) = tdb.find_type(&trait_impl.name.name)
+            {
+                traits.push(trait_type.id);
+            }
+        }
+    }
+
+    traits
+}
+
+/// Gets the ID of the type which is referenced at the given location.
+fn type_at(state: &State, location: Location) -> Option<NodeId> {
+    let sym = state.checked.symbols.lookup_position(location)?;
+    let SymbolKind::Type { name } = &sym.kind else {
+        return None;
+    };
+
+    let package = state.checked.graph.packages.get(&sym.location.file.package)?;
+
+    package.tcx.tdb().find_type(name).map(|ty| ty.id)
+}
+
+fn items_of(state: &State, type_ids: Vec<NodeId>) -> Vec<TypeHierarchyItem> {
+    let mut unique_ids = Vec::with_capacity(type_ids.len());
+
+    for type_id in type_ids {
+        if !unique_ids.contains(&type_id) {
+            unique_ids.push(type_id);
+        }
+    }
+
+    unique_ids
+        .into_iter()
+        .filter_map(|type_id| type_hierarchy_item(state, type_id))
+        .collect()
+}
+
+/// Builds the type hierarchy item of the type with the given ID, from the
+/// package which declares it.
+fn type_hierarchy_item(state: &State, type_id: NodeId) -> Option<TypeHierarchyItem> {
+    let (type_def, span) =
+        state
+            .checked
+            .graph
+            .packages
+            .values()
+            .find_map(|package| match package.tcx.hir_node(type_id) {
+                Some(node @ lume_hir::Node::Type(type_def)) => Some((type_def, node.location())),
+                _ => None,
+            })?;
+
+    let (name, kind) = match type_def {
+        lume_hir::TypeDefinition::Struct(struct_def) => (&struct_def.name, LspSymbolKind::STRUCT),
+        lume_hir::TypeDefinition::Trait(trait_def) => (&trait_def.name, LspSymbolKind::INTERFACE),
+        lume_hir::TypeDefinition::Enum(enum_def) => (&enum_def.name, LspSymbolKind::ENUM),
+    };
+
+    let detail = match type_def {
+        lume_hir::TypeDefinition::Struct(struct_def) if struct_def.builtin => format!("builtin {name:+}"),
+        _ => format!("{name:+}"),
+    };
+
+    let name_location = location_of_type_def(type_def);
+
+    Some(TypeHierarchyItem {
+        name: name.to_string(),
+        kind,
+        tags: None,
+        detail: Some(detail),
+        uri: state.lsp_location_of(name_location)?.uri,
+        range: state.lsp_range_of(span),
+        selection_range: state.lsp_range_of(name_location),
+        data: None,
+    })
+}
+
+/// Builds a synthetic type hierarchy item for the builtin type at the given
+/// location, which has no declaration within the HIR.
+///
+/// Since there is no declaration to point to, the item points to the
+/// referenced type itself, so its implemented traits can still be browsed.
+fn builtin_type_hierarchy_item(state: &State, location: Location) -> Option<TypeHierarchyItem> {
+    let sym = state.checked.symbols.lookup_position(location)?;
+    let SymbolKind::Type { name } = &sym.kind else {
+        return None;
+    };
+
+    Some(TypeHierarchyItem {
+        name: name.to_string(),
+        kind: LspSymbolKind::STRUCT,
+        tags: None,
+        detail: Some(format!("builtin {name:+}")),
+        uri: state.lsp_location_of(sym.location)?.uri,
+        range: state.lsp_range_of(sym.location),
+        selection_range: state.lsp_range_of(sym.location),
+        data: None,
+    })
+}
@@ -1,5 +1,8 @@
+use std::collections::HashSet;
+
 use lume_errors::Result;
 use lume_hir::*;
+use lume_span::SourceFileId;
 
 pub(crate) trait Visitor {
     fn visit_node(&mut self, _node: &Node) -> Result<()> {
@@ -39,6 +42,17 @@ pub(crate) fn traverse<'hir, V: Visitor>(hir: &Map, visitor: &mut V) -> Result<(
     Ok(())
 }
 
+/// Traverses only the nodes which are declared within any of the given files.
+pub(crate) fn traverse_files<V: Visitor>(hir: &Map, files: &HashSet<SourceFileId>, visitor: &mut V) -> Result<()> {
+    for node in hir.nodes().values() {
+        if files.contains(&node.location().file.id) {
+            traverse_node(hir, visitor, node)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn traverse_node<'hir, V: Visitor>(hir: &Map, visitor: &mut V, node: &Node) -> Result<()> {
     visitor.visit_node(node)?;
 
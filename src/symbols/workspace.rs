@@ -0,0 +1,47 @@
+use lsp_types::{OneOf, WorkspaceSymbol};
+use lume_errors::Result;
+
+use crate::state::State;
+use crate::symbols::outline::{Declaration, declarations_of};
+
+/// Maximum number of symbols returned from a single workspace symbol query.
+pub(crate) const MAX_WORKSPACE_SYMBOLS: usize = 256;
+
+/// Searches all packages in the workspace for declarations whose name contains
+/// the given query, ignoring case.
+pub(crate) fn workspace_symbols(state: &State, query: &str) -> Result<Vec<WorkspaceSymbol>> {
+    let query = query.to_lowercase();
+    let mut symbols = Vec::new();
+
+    for package in state.checked.graph.packages.values() {
+        for declaration in declarations_of(package.tcx.hir(), None)? {
+            collect_matching(state, declaration, &query, &mut symbols);
+
+            if symbols.len() >= MAX_WORKSPACE_SYMBOLS {
+                symbols.truncate(MAX_WORKSPACE_SYMBOLS);
+                return Ok(symbols);
+            }
+        }
+    }
+
+    Ok(symbols)
+}
+
+fn collect_matching(state: &State, declaration: Declaration, query: &str, symbols: &mut Vec<WorkspaceSymbol>) {
+    if declaration.name.to_lowercase().contains(query)
+        && let Some(location) = state.lsp_location_of(declaration.location)
+    {
+        symbols.push(WorkspaceSymbol {
+            name: declaration.name,
+            kind: declaration.kind,
+            tags: None,
+            container_name: declaration.container,
+            location: OneOf::Left(location),
+            data: None,
+        });
+    }
+
+    for child in declaration.children {
+        collect_matching(state, child, query, symbols);
+    }
+}
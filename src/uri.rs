@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use lsp_types::Uri;
+
+/// Converts the given absolute file path into a `file://` URI.
+///
+/// Backslashes are replaced with forward slashes and drive-letter roots, such
+/// as `C:\`, are prefixed with a slash, so Windows paths become valid URIs.
+/// Characters which aren't allowed within the path of a URI are
+/// percent-encoded.
+///
+/// Drive letters are written in lowercase with an encoded colon, such as
+/// `/c%3A/`, which is the form sent by most clients.
+pub(crate) fn uri_from_path(path: &Path) -> Option<Uri> {
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    let (drive, path) = if has_drive_letter(&path) {
+        let letter = path[..1].to_ascii_lowercase();
+
+        (format!("/{letter}%3A"), &path[2..])
+    } else {
+        (String::new(), path.as_str())
+    };
+
+    let mut uri = format!("file://{drive}");
+
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    Uri::from_str(&uri).ok()
+}
+
+/// Converts the given `file://` URI into a file path, decoding all
+/// percent-encoded characters.
+///
+/// Drive-letter roots, such as `/C:/` or `/c%3A/`, are converted back into
+/// Windows paths without the leading slash and with an uppercase drive letter,
+/// so both forms resolve to the same path.
+pub(crate) fn path_from_uri(uri: &Uri) -> PathBuf {
+    let path = percent_decode(uri.path().as_str());
+
+    match path.strip_prefix('/') {
+        Some(stripped) if has_drive_letter(stripped) => {
+            PathBuf::from(format!("{}{}", stripped[..1].to_ascii_uppercase(), &stripped[1..]))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Determines whether the given path starts with a Windows drive letter, such
+/// as `C:`.
+fn has_drive_letter(path: &str) -> bool {
+    let mut chars = path.chars();
+
+    matches!((chars.next(), chars.next()), (Some(letter), Some(':')) if letter.is_ascii_alphabetic())
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'%'
+            && let Some(hex) = text.get(idx + 1..idx + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            decoded.push(byte);
+            idx += 3;
+            continue;
+        }
+
+        decoded.push(bytes[idx]);
+        idx += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(text: &str) -> Uri {
+        Uri::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn unix_path_to_uri() {
+        let uri = uri_from_path(Path::new("/home/user/project/main.lm")).unwrap();
+
+        assert_eq!(uri.as_str(), "file:///home/user/project/main.lm");
+    }
+
+    #[test]
+    fn unix_uri_to_path() {
+        let path = path_from_uri(&uri("file:///home/user/project/main.lm"));
+
+        assert_eq!(path, PathBuf::from("/home/user/project/main.lm"));
+    }
+
+    #[test]
+    fn windows_path_to_uri() {
+        let uri = uri_from_path(Path::new("C:\\Users\\user\\main.lm")).unwrap();
+
+        assert_eq!(uri.as_str(), "file:///c%3A/Users/user/main.lm");
+    }
+
+    #[test]
+    fn drive_letter_uris_resolve_to_same_path() {
+        let expected = PathBuf::from("C:/Users/user/main.lm");
+
+        assert_eq!(path_from_uri(&uri("file:///c%3A/Users/user/main.lm")), expected);
+        assert_eq!(path_from_uri(&uri("file:///c%3a/Users/user/main.lm")), expected);
+        assert_eq!(path_from_uri(&uri("file:///C:/Users/user/main.lm")), expected);
+        assert_eq!(path_from_uri(&uri("file:///c:/Users/user/main.lm")), expected);
+    }
+
+    #[test]
+    fn drive_letter_round_trips() {
+        let client_uri = uri("file:///c%3A/Users/user/main.lm");
+        let path = path_from_uri(&client_uri);
+
+        assert_eq!(uri_from_path(&path), Some(client_uri));
+        assert_eq!(uri_from_path(Path::new("C:/Users/user/main.lm")), uri_from_path(&path));
+    }
+
+    #[test]
+    fn drive_letter_detection() {
+        assert!(has_drive_letter("C:/"));
+        assert!(has_drive_letter("z:"));
+        assert!(!has_drive_letter("/C:/"));
+        assert!(!has_drive_letter("1:/"));
+        assert!(!has_drive_letter("C"));
+    }
+
+    #[test]
+    fn paths_with_spaces_and_non_ascii_round_trip() {
+        let path = Path::new("/home/user/my projects/café/über main.lm");
+        let uri = uri_from_path(path).unwrap();
+
+        assert_eq!(
+            uri.as_str(),
+            "file:///home/user/my%20projects/caf%C3%A9/%C3%BCber%20main.lm"
+        );
+        assert_eq!(path_from_uri(&uri), path);
+    }
+
+    #[test]
+    fn invalid_escapes_are_kept() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("%41%42"), "AB");
+    }
+}
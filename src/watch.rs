@@ -0,0 +1,101 @@
+use lsp_server::{Message, RequestId};
+use lsp_types::request::{RegisterCapability, Request as _};
+use lsp_types::*;
+
+use crate::state::State;
+
+impl State {
+    /// Asks the client to notify the server about changes to source files and
+    /// `Arcfile`s on disk, including changes made outside of the editor.
+    pub(crate) fn register_file_watchers(&self) {
+        // Brace groups with fewer than two alternatives aren't supported by all
+        // clients, so each extension is watched by a separate pattern instead.
+        let watchers = self
+            .config
+            .file_extensions
+            .iter()
+            .map(|extension| format!("**/*.{extension}"))
+            .chain(std::iter::once(String::from("**/Arcfile")))
+            .map(|pattern| FileSystemWatcher {
+                glob_pattern: GlobPattern::String(pattern),
+                kind: None,
+            })
+            .collect::<Vec<_>>();
+
+        let registration = Registration {
+            id: String::from("lume/watched-files"),
+            method: String::from("workspace/didChangeWatchedFiles"),
+            register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions { watchers }).ok(),
+        };
+
+        // The response is handled in the listening loop.
+        let request = lsp_server::Request::new(
+            RequestId::from(String::from("lume/register-watchers")),
+            RegisterCapability::METHOD.to_owned(),
+            RegistrationParams {
+                registrations: vec![registration],
+            },
+        );
+
+        if let Err(err) = self.dispatcher.send(Message::Request(request)) {
+            log::error!("could not register file watchers: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam::channel::Receiver;
+
+    use super::*;
+    use crate::state::tests::test_state;
+
+    fn watched_patterns(receiver: &Receiver<Message>) -> Vec<String> {
+        let Ok(Message::Request(request)) = receiver.try_recv() else {
+            panic!("expected registration request");
+        };
+
+        let params: RegistrationParams = serde_json::from_value(request.params).unwrap();
+        let options: DidChangeWatchedFilesRegistrationOptions =
+            serde_json::from_value(params.registrations[0].register_options.clone().unwrap()).unwrap();
+
+        options
+            .watchers
+            .into_iter()
+            .map(|watcher| match watcher.glob_pattern {
+                GlobPattern::String(pattern) => pattern,
+                GlobPattern::Relative(_) => panic!("expected string pattern"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn watches_each_extension() {
+        let (mut state, receiver) = test_state();
+        state.config.file_extensions = vec![String::from("lm"), String::from("arc")];
+
+        state.register_file_watchers();
+
+        assert_eq!(watched_patterns(&receiver), ["**/*.lm", "**/*.arc", "**/Arcfile"]);
+    }
+
+    #[test]
+    fn watches_single_extension() {
+        let (mut state, receiver) = test_state();
+        state.config.file_extensions = vec![String::from("lm")];
+
+        state.register_file_watchers();
+
+        assert_eq!(watched_patterns(&receiver), ["**/*.lm", "**/Arcfile"]);
+    }
+
+    #[test]
+    fn watches_only_arcfiles_without_extensions() {
+        let (mut state, receiver) = test_state();
+        state.config.file_extensions = Vec::new();
+
+        state.register_file_watchers();
+
+        assert_eq!(watched_patterns(&receiver), ["**/Arcfile"]);
+    }
+}